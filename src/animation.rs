@@ -0,0 +1,212 @@
+//! Frame-per-column rendering of an overlay table, assembled into GIF, APNG or numbered PNGs.
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use cairo::{Context as CairoContext, ImageSurface};
+
+use crate::overlay::Fills;
+use crate::{create_png_surface, draw_text_left, FONT_MAIN_PX};
+
+/// Height of the band above the map holding the frame's timestamp.
+const CAPTION_BAND_PX: f64 = FONT_MAIN_PX * 1.8;
+const CAPTION_MARGIN_PX: f64 = 10.0;
+
+enum Format {
+    Gif,
+    Apng,
+    /// One `frame_NNN.png` per frame in a directory.
+    Sequence,
+}
+
+impl Format {
+    fn for_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("gif") => Format::Gif,
+            Some("png" | "apng") => Format::Apng,
+            _ => Format::Sequence,
+        }
+    }
+}
+
+/// Render one frame per `(caption, fills)` pair and write them to `path`.
+///
+/// The format follows the extension: `.gif`, `.png`/`.apng` (animated PNG), or anything else
+/// for a directory of numbered PNG frames. `render` draws the map for a frame's fills.
+pub(crate) fn write_animation<F>(
+    path: &Path,
+    frames: &[(String, Fills)],
+    width: f64,
+    height: f64,
+    delay_ms: u32,
+    render: F,
+) -> Result<()>
+where
+    F: Fn(&CairoContext, &Fills) -> Result<()>,
+{
+    let width_px = width.ceil() as u32;
+    let height_px = (height + CAPTION_BAND_PX).ceil() as u32;
+    let mut images = Vec::with_capacity(frames.len());
+    for (caption, fills) in frames {
+        let (surface, ctx) = create_png_surface(width_px as i32, height_px as i32)?;
+        draw_text_left(
+            &ctx,
+            CAPTION_MARGIN_PX,
+            CAPTION_BAND_PX / 2.0,
+            caption,
+            FONT_MAIN_PX,
+        )?;
+        ctx.save()?;
+        ctx.translate(0.0, CAPTION_BAND_PX);
+        render(&ctx, fills)?;
+        ctx.restore()?;
+        drop(ctx);
+        images.push(surface);
+    }
+
+    match Format::for_path(path) {
+        Format::Gif => write_gif(path, &mut images, width_px, height_px, delay_ms),
+        Format::Apng => write_apng(path, &mut images, width_px, height_px, delay_ms),
+        Format::Sequence => write_sequence(path, &images),
+    }
+}
+
+fn write_gif(
+    path: &Path,
+    images: &mut [ImageSurface],
+    width: u32,
+    height: u32,
+    delay_ms: u32,
+) -> Result<()> {
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(anyhow!(
+            "Animation frames are {width}x{height} px, larger than GIF allows"
+        ));
+    };
+    let file = fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &[])
+        .context("Failed to write GIF header")?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .context("Failed to write GIF header")?;
+    for image in images {
+        let mut pixels = rgba_pixels(image)?;
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+        // GIF delays are in hundredths of a second.
+        frame.delay = (delay_ms / 10).min(u16::MAX as u32) as u16;
+        encoder
+            .write_frame(&frame)
+            .context("Failed to write GIF frame")?;
+    }
+    Ok(())
+}
+
+fn write_apng(
+    path: &Path,
+    images: &mut [ImageSurface],
+    width: u32,
+    height: u32,
+    delay_ms: u32,
+) -> Result<()> {
+    let file = fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(images.len() as u32, 0)
+        .context("Failed to set up APNG")?;
+    encoder
+        .set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000)
+        .context("Failed to set up APNG")?;
+    let mut writer = encoder
+        .write_header()
+        .context("Failed to write APNG header")?;
+    for image in images {
+        writer
+            .write_image_data(&rgba_pixels(image)?)
+            .context("Failed to write APNG frame")?;
+    }
+    writer.finish().context("Failed to write APNG")
+}
+
+fn write_sequence(dir: &Path, images: &[ImageSurface]) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    for (i, image) in images.iter().enumerate() {
+        let frame_path: PathBuf = dir.join(format!("frame_{:03}.png", i + 1));
+        let mut file = fs::File::create(&frame_path)
+            .with_context(|| format!("Failed to create {:?}", frame_path))?;
+        image
+            .write_to_png(&mut file)
+            .context("Failed to write PNG")?;
+    }
+    Ok(())
+}
+
+/// Straight-alpha RGBA bytes from a Cairo ARGB32 surface (premultiplied, native-endian words).
+fn rgba_pixels(image: &mut ImageSurface) -> Result<Vec<u8>> {
+    image.flush();
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let stride = image.stride() as usize;
+    let data = image.data().context("Failed to read frame pixels")?;
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for row in data.chunks(stride).take(height) {
+        for word in row[..width * 4].chunks_exact(4) {
+            let argb = u32::from_ne_bytes([word[0], word[1], word[2], word[3]]);
+            let alpha = (argb >> 24) as u8;
+            let channel = |shift: u32| {
+                let value = (argb >> shift) & 0xff;
+                if alpha == 0 {
+                    0
+                } else {
+                    ((value * 255 + alpha as u32 / 2) / alpha as u32).min(255) as u8
+                }
+            };
+            pixels.extend_from_slice(&[channel(16), channel(8), channel(0), alpha]);
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert!(matches!(Format::for_path(Path::new("a.GIF")), Format::Gif));
+        assert!(matches!(
+            Format::for_path(Path::new("a.apng")),
+            Format::Apng
+        ));
+        assert!(matches!(Format::for_path(Path::new("a.png")), Format::Apng));
+        assert!(matches!(
+            Format::for_path(Path::new("frames")),
+            Format::Sequence
+        ));
+    }
+
+    #[test]
+    fn rgba_pixels_undo_premultiplied_alpha() {
+        let (mut surface, ctx) = create_png_surface(2, 1).unwrap();
+        ctx.set_operator(cairo::Operator::Source);
+        ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+        ctx.paint().unwrap();
+        ctx.set_source_rgba(1.0, 0.5, 0.0, 0.5);
+        ctx.rectangle(0.0, 0.0, 1.0, 1.0);
+        ctx.fill().unwrap();
+        drop(ctx);
+        let pixels = rgba_pixels(&mut surface).unwrap();
+        let (red, green, blue, alpha) = (pixels[0], pixels[1], pixels[2], pixels[3]);
+        assert_eq!((red, blue), (255, 0));
+        assert!(green.abs_diff(128) <= 1, "green {green}");
+        assert!(alpha.abs_diff(128) <= 1, "alpha {alpha}");
+        assert_eq!(&pixels[4..], [0, 0, 0, 0]);
+    }
+}
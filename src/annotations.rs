@@ -0,0 +1,184 @@
+//! Notes and MIRIAM cross-references attached to glyphs, and their SVG, PDF and JSON outputs.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use cairo::Context as CairoContext;
+use serde_json::{json, Value};
+
+use crate::sbml::attr;
+use crate::{insert_svg_overlay, xml_escape, Glyph, PixelRect};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const IDENTIFIERS_ORG: &str = "https://identifiers.org/";
+
+/// A cross-reference from an RDF annotation, e.g. `bqbiol:is` `urn:miriam:uniprot:P04637`.
+#[derive(Clone, Debug)]
+pub(crate) struct Xref {
+    pub(crate) qualifier: String,
+    pub(crate) uri: String,
+}
+
+impl Xref {
+    /// Resolvable link for the resource; MIRIAM URNs go through identifiers.org.
+    pub(crate) fn url(&self) -> String {
+        match self.uri.strip_prefix("urn:miriam:") {
+            Some(rest) => {
+                let rest = rest.replace("%3A", ":").replace("%3a", ":");
+                match rest.split_once(':') {
+                    Some((namespace, id)) => format!("{IDENTIFIERS_ORG}{namespace}:{id}"),
+                    None => format!("{IDENTIFIERS_ORG}{rest}"),
+                }
+            }
+            None => self.uri.clone(),
+        }
+    }
+}
+
+/// Text of an element's `<notes>` child, with whitespace collapsed.
+pub(crate) fn notes_text(node: &roxmltree::Node) -> Option<String> {
+    let notes = node.children().find(|child| child.has_tag_name("notes"))?;
+    let text = notes
+        .descendants()
+        .filter(|child| child.is_text())
+        .filter_map(|child| child.text())
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Resources listed in RDF under an element's `<annotation>` or `<extension>` children.
+///
+/// The qualifier is the element holding the `rdf:Bag`, written with its document prefix.
+pub(crate) fn xrefs(node: &roxmltree::Node) -> Vec<Xref> {
+    node.children()
+        .filter(|child| child.has_tag_name("annotation") || child.has_tag_name("extension"))
+        .flat_map(|child| child.descendants())
+        .filter(|item| {
+            item.tag_name().name() == "li" && item.tag_name().namespace() == Some(RDF_NS)
+        })
+        .filter_map(|item| {
+            let uri = attr(&item, "resource")?.trim().to_string();
+            let qualifier = item
+                .ancestors()
+                .skip(1)
+                .find(|ancestor| {
+                    ancestor.is_element() && ancestor.tag_name().namespace() != Some(RDF_NS)
+                })
+                .map(|ancestor| {
+                    let name = ancestor.tag_name();
+                    match name.namespace().and_then(|ns| ancestor.lookup_prefix(ns)) {
+                        Some(prefix) if !prefix.is_empty() => format!("{prefix}:{}", name.name()),
+                        _ => name.name().to_string(),
+                    }
+                })
+                .unwrap_or_default();
+            (!uri.is_empty()).then_some(Xref { qualifier, uri })
+        })
+        .collect()
+}
+
+pub(crate) fn has_annotations(glyph: &Glyph) -> bool {
+    glyph.notes.is_some() || !glyph.xrefs.is_empty()
+}
+
+/// Add an invisible hit area per annotated glyph to an SVG, carrying `<title>` (the label),
+/// `<desc>` (the notes) and a `data-xref` list, linked to the first cross-reference.
+pub(crate) fn add_svg_annotations(svg_path: &Path, items: &[(PixelRect, &Glyph)]) -> Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let mut overlay = String::new();
+    for (rect, glyph) in items {
+        let urls: Vec<String> = glyph.xrefs.iter().map(Xref::url).collect();
+        let mut area = format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"#000\" fill-opacity=\"0\" data-glyph-id=\"{}\"",
+            rect.x0,
+            rect.y0,
+            rect.width,
+            rect.height,
+            xml_escape(&glyph.id)
+        );
+        if !urls.is_empty() {
+            let _ = write!(area, " data-xref=\"{}\"", xml_escape(&urls.join(" ")));
+        }
+        let title = if glyph.label.trim().is_empty() {
+            &glyph.id
+        } else {
+            &glyph.label
+        };
+        let _ = write!(area, "><title>{}</title>", xml_escape(title));
+        if let Some(notes) = &glyph.notes {
+            let _ = write!(area, "<desc>{}</desc>", xml_escape(notes));
+        }
+        area.push_str("</rect>");
+        match urls.first() {
+            Some(url) => {
+                let href = xml_escape(url);
+                let _ = writeln!(
+                    overlay,
+                    "<a xlink:href=\"{href}\" href=\"{href}\" style=\"cursor:pointer\">{area}</a>"
+                );
+            }
+            None => {
+                let _ = writeln!(overlay, "{area}");
+            }
+        }
+    }
+    insert_svg_overlay(svg_path, &overlay)
+}
+
+/// Tag each annotated glyph's area as a PDF link to its first cross-reference.
+pub(crate) fn add_pdf_links(ctx: &CairoContext, items: &[(PixelRect, &Glyph)]) {
+    for (rect, glyph) in items {
+        let Some(xref) = glyph.xrefs.first() else {
+            continue;
+        };
+        let url = xref.url().replace('\\', "\\\\").replace('\'', "\\'");
+        let attributes = format!(
+            "uri='{url}' rect=[{:.2} {:.2} {:.2} {:.2}]",
+            rect.x0, rect.y0, rect.width, rect.height
+        );
+        ctx.tag_begin(cairo::CAIRO_TAG_LINK, &attributes);
+        ctx.tag_end(cairo::CAIRO_TAG_LINK);
+    }
+}
+
+/// Write the annotated glyphs with their pixel area in the rendered image as JSON.
+pub(crate) fn write_annotations_json(path: &Path, items: &[(PixelRect, &Glyph)]) -> Result<()> {
+    // Pixel positions to two decimals, which is finer than any viewer can use.
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    let items: Vec<Value> = items
+        .iter()
+        .map(|(rect, glyph)| {
+            let mut item = json!({
+                "id": glyph.id,
+                "class": glyph.class_name,
+                "label": glyph.label,
+                "rect": {
+                    "x": round(rect.x0),
+                    "y": round(rect.y0),
+                    "width": round(rect.width),
+                    "height": round(rect.height),
+                },
+            });
+            if let Some(notes) = &glyph.notes {
+                item["notes"] = json!(notes);
+            }
+            item["xrefs"] = glyph
+                .xrefs
+                .iter()
+                .map(
+                    |xref| json!({"qualifier": xref.qualifier, "uri": xref.uri, "url": xref.url()}),
+                )
+                .collect();
+            item
+        })
+        .collect();
+    let mut out = serde_json::to_string_pretty(&items).context("Failed to encode JSON")?;
+    out.push('\n');
+    fs::write(path, out).with_context(|| format!("Failed to write {:?}", path))
+}
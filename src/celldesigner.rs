@@ -0,0 +1,708 @@
+//! CellDesigner import: SBML with CellDesigner's layout extension, drawn as SBGN PD at the
+//! alias positions CellDesigner stored.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use roxmltree::{Document, Node};
+
+use crate::annotations::{notes_text, xrefs, Xref};
+use crate::layout::{glyph_index, straight_path};
+use crate::sbml::{attr, list_items};
+use crate::{Arc, BBox, Glyph};
+
+const CELLDESIGNER_NS: &str = "http://www.sbml.org/2001/ns/celldesigner";
+const PROCESS_SIZE: f64 = 24.0;
+const GATE_SIZE: f64 = 30.0;
+
+/// Whether the document is SBML carrying CellDesigner's species aliases.
+///
+/// Other CellDesigner annotations alone, as left in models re-exported with a Layout
+/// package, are not enough to draw from, so such models go to the SBML importer.
+pub(crate) fn is_celldesigner(doc: &Document) -> bool {
+    doc.root_element().has_tag_name("sbml")
+        && doc.descendants().any(|node| {
+            node.has_tag_name("listOfSpeciesAliases")
+                && node.tag_name().namespace() == Some(CELLDESIGNER_NS)
+        })
+}
+
+/// Convert a CellDesigner model to SBGN PD glyphs and arcs.
+///
+/// Every alias becomes a glyph at its CellDesigner bounds (aliases of one species are
+/// clones), modifications become state variables, homodimers become multimers, and each
+/// reaction becomes a process node between its base reactants and products. Arcs get straight
+/// paths between the aliases and the process connectors, so `--layout never` still draws
+/// them; `--layout always` reroutes them after moving the glyphs.
+pub(crate) fn parse_celldesigner(doc: &Document) -> Result<(Vec<Glyph>, Vec<Arc>)> {
+    let model = doc
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("model"))
+        .ok_or_else(|| anyhow!("No <model> element found in CellDesigner file"))?;
+    let extension = model
+        .children()
+        .find(|node| node.has_tag_name("annotation"))
+        .and_then(|node| {
+            node.children()
+                .find(|child| child.has_tag_name("extension"))
+        })
+        .ok_or_else(|| anyhow!("No CellDesigner layout extension found"))?;
+
+    let compartment_names: HashMap<&str, String> = model
+        .descendants()
+        .filter(|node| {
+            node.has_tag_name("compartment") && node.tag_name().namespace() != Some(CELLDESIGNER_NS)
+        })
+        .filter_map(|node| {
+            let id = attr(&node, "id")?;
+            Some((id, decode_name(attr(&node, "name").unwrap_or(id))))
+        })
+        .collect();
+    let proteins = residue_names(extension);
+    let species = species_table(&model, extension);
+
+    let mut glyphs = Vec::new();
+
+    for alias in list_items(&extension, "listOfCompartmentAliases", "compartmentAlias") {
+        let Some(id) = attr(&alias, "id") else {
+            continue;
+        };
+        let label = attr(&alias, "compartment")
+            .and_then(|compartment| compartment_names.get(compartment).cloned())
+            .unwrap_or_default();
+        glyphs.push(Glyph::new(id, "compartment", bounds(&alias), &label));
+    }
+
+    let aliases: Vec<Node> = list_items(
+        &extension,
+        "listOfComplexSpeciesAliases",
+        "complexSpeciesAlias",
+    )
+    .chain(list_items(
+        &extension,
+        "listOfSpeciesAliases",
+        "speciesAlias",
+    ))
+    .collect();
+    let mut alias_counts: HashMap<&str, usize> = HashMap::new();
+    for alias in &aliases {
+        if let Some(species) = attr(alias, "species") {
+            *alias_counts.entry(species).or_default() += 1;
+        }
+    }
+    for alias in &aliases {
+        let (Some(id), Some(species_id)) = (attr(alias, "id"), attr(alias, "species")) else {
+            continue;
+        };
+        let Some(info) = species.get(species_id) else {
+            continue;
+        };
+        let class_name = if info.homodimer > 1 && supports_multimer(info.class_name) {
+            format!("{} multimer", info.class_name)
+        } else {
+            info.class_name.to_string()
+        };
+        let mut glyph = Glyph::new(id, &class_name, bounds(alias), &info.name);
+        glyph.parent_id = attr(alias, "complexSpeciesAlias").map(str::to_string);
+        glyph.compartment_ref = attr(alias, "compartmentAlias").map(str::to_string);
+        glyph.has_clone = alias_counts.get(species_id).copied().unwrap_or(0) > 1
+            && info.class_name != "source and sink";
+        glyph.notes = info.notes.clone();
+        glyph.xrefs = info.xrefs.clone();
+        glyphs.push(glyph);
+
+        if info.homodimer > 1 {
+            let mut unit = Glyph::new(
+                &format!("{id}_multimer"),
+                "unit of information",
+                None,
+                &format!("N:{}", info.homodimer),
+            );
+            unit.parent_id = Some(id.to_string());
+            glyphs.push(unit);
+        }
+        for (index, (residue, state)) in info.modifications.iter().enumerate() {
+            let mut variable =
+                Glyph::new(&format!("{id}_state_{index}"), "state variable", None, "");
+            variable.parent_id = Some(id.to_string());
+            variable.state_value = Some(state_abbreviation(state).to_string());
+            variable.state_variable = info
+                .protein
+                .as_deref()
+                .and_then(|protein| proteins.get(&(protein.to_string(), residue.clone())))
+                .cloned()
+                .filter(|name| !name.is_empty());
+            glyphs.push(variable);
+        }
+    }
+
+    let mut arcs = Vec::new();
+    for reaction in list_items(&model, "listOfReactions", "reaction") {
+        let Some(reaction_id) = attr(&reaction, "id") else {
+            continue;
+        };
+        let Some(cd) = reaction
+            .children()
+            .find(|node| node.has_tag_name("annotation"))
+            .and_then(|node| {
+                node.children()
+                    .find(|child| child.has_tag_name("extension"))
+            })
+        else {
+            continue;
+        };
+        let reaction_type = child_text(&cd, "reactionType").unwrap_or("STATE_TRANSITION");
+        let reactants = reaction_aliases(&cd, "baseReactants", "baseReactant");
+        let products = reaction_aliases(&cd, "baseProducts", "baseProduct");
+        let process_bbox = match (
+            mean_center(&glyphs, &reactants[..reactants.len().min(1)]),
+            mean_center(&glyphs, &products[..products.len().min(1)]),
+        ) {
+            (Some(a), Some(b)) => Some(square_at(midpoint(a, b), PROCESS_SIZE)),
+            _ => None,
+        };
+        glyphs.push(Glyph::new(
+            reaction_id,
+            reaction_class(reaction_type),
+            process_bbox,
+            "",
+        ));
+
+        let mut arc = |class_name: &str, source: &str, target: &str| {
+            arcs.push(Arc {
+                id: format!("{reaction_id}_{}", arcs.len()),
+                class_name: class_name.to_string(),
+                source: Some(source.to_string()),
+                target: Some(target.to_string()),
+                points: Vec::new(),
+                glyphs: Vec::new(),
+                ports: Vec::new(),
+                extras: Vec::new(),
+            });
+        };
+        let linked_reactants = reaction_aliases(&cd, "listOfReactantLinks", "reactantLink");
+        let linked_products = reaction_aliases(&cd, "listOfProductLinks", "productLink");
+        for alias in reactants.iter().chain(&linked_reactants) {
+            arc("consumption", alias, reaction_id);
+        }
+        for alias in products.iter().chain(&linked_products) {
+            arc("production", reaction_id, alias);
+        }
+
+        let modifications: Vec<Node> =
+            list_items(&cd, "listOfModification", "modification").collect();
+        let mut gated: HashSet<String> = HashSet::new();
+        for (index, modification) in modifications.iter().enumerate() {
+            let kind = attr(modification, "type").unwrap_or_default();
+            let Some(gate) = kind.strip_prefix("BOOLEAN_LOGIC_GATE_") else {
+                continue;
+            };
+            let gate_class = match gate {
+                "AND" => "and",
+                "OR" => "or",
+                "NOT" => "not",
+                _ => continue,
+            };
+            let gate_id = format!("{reaction_id}_gate_{index}");
+            let inputs = split_ids(attr(modification, "aliases"));
+            let mut gate = Glyph::new(&gate_id, gate_class, None, "");
+            // Put the gate halfway between its inputs and the process it controls, with
+            // its connectors along that direction.
+            if let (Some(from), Some(process)) = (mean_center(&glyphs, &inputs), process_bbox) {
+                let to = (process.x + process.w / 2.0, process.y + process.h / 2.0);
+                gate.bbox = Some(square_at(midpoint(from, to), GATE_SIZE));
+                let horizontal = (to.0 - from.0).abs() >= (to.1 - from.1).abs();
+                let orientation = if horizontal { "horizontal" } else { "vertical" };
+                gate.orientation = Some(orientation.to_string());
+            }
+            glyphs.push(gate);
+            for alias in inputs {
+                arc("logic arc", &alias, &gate_id);
+                gated.insert(alias);
+            }
+            let class_name =
+                modifier_class(attr(modification, "modificationType").unwrap_or_default());
+            arc(class_name, &gate_id, reaction_id);
+        }
+        for modification in &modifications {
+            let kind = attr(modification, "type").unwrap_or_default();
+            if kind.starts_with("BOOLEAN_LOGIC_GATE_") {
+                continue;
+            }
+            for alias in split_ids(attr(modification, "aliases")) {
+                // Gate members are already wired through their logic node.
+                if !gated.contains(&alias) {
+                    arc(modifier_class(kind), &alias, reaction_id);
+                }
+            }
+        }
+    }
+    let index = glyph_index(&glyphs);
+    for arc in &mut arcs {
+        arc.points = straight_path(&glyphs, &index, arc).unwrap_or_default();
+    }
+    Ok((glyphs, arcs))
+}
+
+struct SpeciesInfo {
+    name: String,
+    class_name: &'static str,
+    protein: Option<String>,
+    homodimer: u32,
+    modifications: Vec<(String, String)>,
+    notes: Option<String>,
+    xrefs: Vec<Xref>,
+}
+
+/// Species from the model and CellDesigner's included (complex member) species.
+fn species_table(model: &Node, extension: Node) -> HashMap<String, SpeciesInfo> {
+    let included = list_items(&extension, "listOfIncludedSpecies", "species");
+    list_items(model, "listOfSpecies", "species")
+        .chain(included)
+        .filter_map(|node| {
+            let id = attr(&node, "id")?;
+            let identity = node
+                .descendants()
+                .find(|child| child.has_tag_name("speciesIdentity"));
+            let class_name = identity
+                .and_then(|identity| child_text(&identity, "class"))
+                .map(species_class)
+                .unwrap_or("unspecified entity");
+            let protein = identity.and_then(|identity| {
+                identity
+                    .children()
+                    .find(|child| {
+                        matches!(
+                            child.tag_name().name(),
+                            "proteinReference"
+                                | "geneReference"
+                                | "rnaReference"
+                                | "antisensernaReference"
+                        )
+                    })
+                    .and_then(|child| child.text())
+                    .map(|text| text.trim().to_string())
+            });
+            let state = identity.and_then(|identity| {
+                identity
+                    .children()
+                    .find(|child| child.has_tag_name("state"))
+            });
+            let homodimer = state
+                .and_then(|state| child_text(&state, "homodimer"))
+                .and_then(|text| text.trim().parse().ok())
+                .unwrap_or(1);
+            let modifications = state
+                .into_iter()
+                .flat_map(|state| {
+                    list_items(&state, "listOfModifications", "modification").collect::<Vec<_>>()
+                })
+                .filter_map(|modification| {
+                    Some((
+                        attr(&modification, "residue")?.to_string(),
+                        attr(&modification, "state")?.to_string(),
+                    ))
+                })
+                .collect();
+            Some((
+                id.to_string(),
+                SpeciesInfo {
+                    name: decode_name(attr(&node, "name").unwrap_or(id)),
+                    class_name,
+                    protein,
+                    homodimer,
+                    modifications,
+                    notes: notes_text(&node),
+                    xrefs: xrefs(&node),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Residue names keyed by (protein/gene/RNA id, residue id).
+fn residue_names(extension: Node) -> HashMap<(String, String), String> {
+    let mut names = HashMap::new();
+    for owner in extension.descendants().filter(|node| {
+        matches!(
+            node.tag_name().name(),
+            "protein" | "gene" | "RNA" | "AntisenseRNA"
+        ) && node.tag_name().namespace() == Some(CELLDESIGNER_NS)
+    }) {
+        let Some(owner_id) = attr(&owner, "id") else {
+            continue;
+        };
+        for residue in owner
+            .descendants()
+            .filter(|node| matches!(node.tag_name().name(), "modificationResidue" | "region"))
+        {
+            if let Some(residue_id) = attr(&residue, "id") {
+                let name = decode_name(attr(&residue, "name").unwrap_or_default());
+                names.insert((owner_id.to_string(), residue_id.to_string()), name);
+            }
+        }
+    }
+    names
+}
+
+fn species_class(class: &str) -> &'static str {
+    match class.trim() {
+        "PROTEIN" | "RECEPTOR" | "ION_CHANNEL" | "TRUNCATED" => "macromolecule",
+        "GENE" | "RNA" | "ANTISENSE_RNA" => "nucleic acid feature",
+        "SIMPLE_MOLECULE" | "ION" | "DRUG" => "simple chemical",
+        "COMPLEX" => "complex",
+        "PHENOTYPE" => "phenotype",
+        "DEGRADED" => "source and sink",
+        _ => "unspecified entity",
+    }
+}
+
+fn supports_multimer(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        "macromolecule" | "nucleic acid feature" | "simple chemical" | "complex"
+    )
+}
+
+fn reaction_class(reaction_type: &str) -> &'static str {
+    match reaction_type.trim() {
+        "KNOWN_TRANSITION_OMITTED" => "omitted process",
+        "UNKNOWN_TRANSITION" => "uncertain process",
+        "HETERODIMER_ASSOCIATION" => "association",
+        "DISSOCIATION" | "TRUNCATION" => "dissociation",
+        _ => "process",
+    }
+}
+
+fn modifier_class(kind: &str) -> &'static str {
+    match kind {
+        "CATALYSIS" | "UNKNOWN_CATALYSIS" => "catalysis",
+        "INHIBITION"
+        | "UNKNOWN_INHIBITION"
+        | "TRANSCRIPTIONAL_INHIBITION"
+        | "TRANSLATIONAL_INHIBITION" => "inhibition",
+        "PHYSICAL_STIMULATION" | "TRANSCRIPTIONAL_ACTIVATION" | "TRANSLATIONAL_ACTIVATION" => {
+            "stimulation"
+        }
+        "TRIGGER" => "necessary stimulation",
+        _ => "modulation",
+    }
+}
+
+/// SBGN state value for a CellDesigner modification state.
+fn state_abbreviation(state: &str) -> &str {
+    match state {
+        "phosphorylated" => "P",
+        "acetylated" => "Ac",
+        "ubiquitinated" => "Ub",
+        "methylated" => "Me",
+        "hydroxylated" => "OH",
+        "glycosylated" => "G",
+        "myristoylated" => "My",
+        "palmytoylated" => "Pa",
+        "prenylated" => "Pr",
+        "protonated" => "H",
+        "sulfated" => "S",
+        "don't care" | "dont_care" => "*",
+        "unknown" => "?",
+        "empty" => "",
+        other => other,
+    }
+}
+
+/// Undo CellDesigner's `_br_`, `_space_` etc. escapes in names.
+fn decode_name(name: &str) -> String {
+    const ESCAPES: [(&str, &str); 13] = [
+        ("_br_", "\n"),
+        ("_BR_", "\n"),
+        ("_space_", " "),
+        ("_underscore_", "_"),
+        ("_plus_", "+"),
+        ("_minus_", "-"),
+        ("_slash_", "/"),
+        ("_alpha_", "α"),
+        ("_beta_", "β"),
+        ("_gamma_", "γ"),
+        ("_delta_", "δ"),
+        ("_kappa_", "κ"),
+        ("_epsilon_", "ε"),
+    ];
+    ESCAPES
+        .iter()
+        .fold(name.to_string(), |text, (escape, value)| {
+            text.replace(escape, value)
+        })
+}
+
+/// Mean center of the glyphs named by `ids` that have a bbox.
+fn mean_center(glyphs: &[Glyph], ids: &[String]) -> Option<(f64, f64)> {
+    let centers: Vec<(f64, f64)> = ids
+        .iter()
+        .filter_map(|id| glyphs.iter().find(|glyph| &glyph.id == id)?.bbox)
+        .map(|bbox| (bbox.x + bbox.w / 2.0, bbox.y + bbox.h / 2.0))
+        .collect();
+    if centers.is_empty() {
+        return None;
+    }
+    let count = centers.len() as f64;
+    Some((
+        centers.iter().map(|center| center.0).sum::<f64>() / count,
+        centers.iter().map(|center| center.1).sum::<f64>() / count,
+    ))
+}
+
+fn midpoint(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+fn square_at(center: (f64, f64), size: f64) -> BBox {
+    BBox {
+        x: center.0 - size / 2.0,
+        y: center.1 - size / 2.0,
+        w: size,
+        h: size,
+    }
+}
+
+fn reaction_aliases(extension: &Node, list: &str, item: &str) -> Vec<String> {
+    list_items(extension, list, item)
+        .filter_map(|node| attr(&node, "alias").map(str::to_string))
+        .collect()
+}
+
+fn split_ids(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn bounds(node: &Node) -> Option<BBox> {
+    let bounds = node.children().find(|child| child.has_tag_name("bounds"))?;
+    let number = |name: &str| attr(&bounds, name)?.parse::<f64>().ok();
+    Some(BBox {
+        x: number("x")?,
+        y: number("y")?,
+        w: number("w")?,
+        h: number("h")?,
+    })
+}
+
+fn child_text<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = r#"<sbml xmlns="http://www.sbml.org/sbml/level2/version4" xmlns:celldesigner="http://www.sbml.org/2001/ns/celldesigner" level="2" version="4">
+<model id="m">
+<annotation><celldesigner:extension>
+<celldesigner:listOfSpeciesAliases>
+<celldesigner:speciesAlias id="sa1" species="s1"><celldesigner:bounds x="0" y="0" w="100" h="50"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa2" species="s2"><celldesigner:bounds x="300" y="0" w="100" h="50"/></celldesigner:speciesAlias>
+</celldesigner:listOfSpeciesAliases>
+</celldesigner:extension></annotation>
+<listOfSpecies>
+<species id="s1" name="A"/>
+<species id="s2" name="B"/>
+</listOfSpecies>
+<listOfReactions>
+<reaction id="re1"><annotation><celldesigner:extension>
+<celldesigner:reactionType>STATE_TRANSITION</celldesigner:reactionType>
+<celldesigner:baseReactants><celldesigner:baseReactant species="s1" alias="sa1"/></celldesigner:baseReactants>
+<celldesigner:baseProducts><celldesigner:baseProduct species="s2" alias="sa2"/></celldesigner:baseProducts>
+</celldesigner:extension></annotation></reaction>
+</listOfReactions>
+</model>
+</sbml>"#;
+
+    /// Complex with members, a clone pair, a phosphorylated homodimer and an AND gate.
+    const MAP: &str = r#"<sbml xmlns="http://www.sbml.org/sbml/level2/version4" xmlns:celldesigner="http://www.sbml.org/2001/ns/celldesigner" level="2" version="4">
+<model id="untitled">
+<annotation>
+<celldesigner:extension>
+<celldesigner:listOfCompartmentAliases>
+<celldesigner:compartmentAlias id="ca1" compartment="c1"><celldesigner:class>SQUARE</celldesigner:class><celldesigner:bounds x="20" y="20" w="620" h="330"/></celldesigner:compartmentAlias>
+</celldesigner:listOfCompartmentAliases>
+<celldesigner:listOfComplexSpeciesAliases>
+<celldesigner:complexSpeciesAlias id="csa1" species="s5" compartmentAlias="ca1"><celldesigner:bounds x="420" y="200" w="180" h="120"/></celldesigner:complexSpeciesAlias>
+</celldesigner:listOfComplexSpeciesAliases>
+<celldesigner:listOfSpeciesAliases>
+<celldesigner:speciesAlias id="sa1" species="s1" compartmentAlias="ca1"><celldesigner:bounds x="60" y="80" w="100" h="50"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa2" species="s2" compartmentAlias="ca1"><celldesigner:bounds x="450" y="80" w="100" h="50"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa3" species="s3" compartmentAlias="ca1"><celldesigner:bounds x="250" y="230" w="90" h="40"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa4" species="s4" compartmentAlias="ca1"><celldesigner:bounds x="60" y="230" w="40" h="40"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa4b" species="s4" compartmentAlias="ca1"><celldesigner:bounds x="60" y="290" w="40" h="40"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa6" species="s6" complexSpeciesAlias="csa1"><celldesigner:bounds x="440" y="220" w="80" h="40"/></celldesigner:speciesAlias>
+<celldesigner:speciesAlias id="sa7" species="s7" complexSpeciesAlias="csa1"><celldesigner:bounds x="480" y="265" w="100" h="40"/></celldesigner:speciesAlias>
+</celldesigner:listOfSpeciesAliases>
+<celldesigner:listOfIncludedSpecies>
+<celldesigner:species id="s6" name="RAS"><celldesigner:annotation><celldesigner:complexSpecies>s5</celldesigner:complexSpecies><celldesigner:speciesIdentity><celldesigner:class>PROTEIN</celldesigner:class><celldesigner:proteinReference>pr3</celldesigner:proteinReference></celldesigner:speciesIdentity></celldesigner:annotation></celldesigner:species>
+<celldesigner:species id="s7" name="GTP"><celldesigner:annotation><celldesigner:complexSpecies>s5</celldesigner:complexSpecies><celldesigner:speciesIdentity><celldesigner:class>SIMPLE_MOLECULE</celldesigner:class></celldesigner:speciesIdentity></celldesigner:annotation></celldesigner:species>
+</celldesigner:listOfIncludedSpecies>
+<celldesigner:listOfProteins>
+<celldesigner:protein id="pr1" name="MEK" type="GENERIC"><celldesigner:listOfModificationResidues><celldesigner:modificationResidue id="rs1" name="S218"/><celldesigner:modificationResidue id="rs2" name="S222"/></celldesigner:listOfModificationResidues></celldesigner:protein>
+</celldesigner:listOfProteins>
+</celldesigner:extension>
+</annotation>
+<listOfCompartments><compartment id="default"/><compartment id="c1" name="cyto_space_plasm" outside="default"/></listOfCompartments>
+<listOfSpecies>
+<species id="s1" name="MEK" compartment="c1"><annotation><celldesigner:extension><celldesigner:speciesIdentity><celldesigner:class>PROTEIN</celldesigner:class><celldesigner:proteinReference>pr1</celldesigner:proteinReference></celldesigner:speciesIdentity></celldesigner:extension></annotation></species>
+<species id="s2" name="MEK" compartment="c1"><annotation><celldesigner:extension><celldesigner:speciesIdentity><celldesigner:class>PROTEIN</celldesigner:class><celldesigner:proteinReference>pr1</celldesigner:proteinReference><celldesigner:state><celldesigner:homodimer>2</celldesigner:homodimer><celldesigner:listOfModifications><celldesigner:modification residue="rs1" state="phosphorylated"/><celldesigner:modification residue="rs2" state="phosphorylated"/></celldesigner:listOfModifications></celldesigner:state></celldesigner:speciesIdentity></celldesigner:extension></annotation></species>
+<species id="s3" name="RAF" compartment="c1"><annotation><celldesigner:extension><celldesigner:speciesIdentity><celldesigner:class>PROTEIN</celldesigner:class></celldesigner:speciesIdentity></celldesigner:extension></annotation></species>
+<species id="s4" name="ATP" compartment="c1"><annotation><celldesigner:extension><celldesigner:speciesIdentity><celldesigner:class>SIMPLE_MOLECULE</celldesigner:class></celldesigner:speciesIdentity></celldesigner:extension></annotation></species>
+<species id="s5" name="RAS_space_complex" compartment="c1"><annotation><celldesigner:extension><celldesigner:speciesIdentity><celldesigner:class>COMPLEX</celldesigner:class></celldesigner:speciesIdentity></celldesigner:extension></annotation></species>
+</listOfSpecies>
+<listOfReactions>
+<reaction id="re1" reversible="false"><annotation><celldesigner:extension>
+<celldesigner:reactionType>STATE_TRANSITION</celldesigner:reactionType>
+<celldesigner:baseReactants><celldesigner:baseReactant species="s1" alias="sa1"/></celldesigner:baseReactants>
+<celldesigner:baseProducts><celldesigner:baseProduct species="s2" alias="sa2"/></celldesigner:baseProducts>
+<celldesigner:listOfReactantLinks><celldesigner:reactantLink reactant="s4" alias="sa4"/></celldesigner:listOfReactantLinks>
+<celldesigner:listOfModification>
+<celldesigner:modification type="BOOLEAN_LOGIC_GATE_AND" modificationType="CATALYSIS" modifiers="s3,s5" aliases="sa3,csa1"/>
+<celldesigner:modification type="CATALYSIS" modifiers="s3" aliases="sa3"/>
+</celldesigner:listOfModification>
+</celldesigner:extension></annotation>
+<listOfReactants><speciesReference species="s1"/></listOfReactants>
+<listOfProducts><speciesReference species="s2"/></listOfProducts>
+</reaction>
+</listOfReactions>
+</model>
+</sbml>"#;
+
+    fn find<'a>(glyphs: &'a [Glyph], id: &str) -> &'a Glyph {
+        glyphs.iter().find(|glyph| glyph.id == id).unwrap()
+    }
+
+    #[test]
+    fn aliases_become_classed_glyphs_with_complex_members_and_clones() {
+        let doc = Document::parse(MAP).unwrap();
+        let (glyphs, _) = parse_celldesigner(&doc).unwrap();
+        let compartment = find(&glyphs, "ca1");
+        assert_eq!(compartment.class_name, "compartment");
+        assert_eq!(compartment.label, "cyto plasm");
+        assert_eq!(find(&glyphs, "sa1").class_name, "macromolecule");
+        assert_eq!(find(&glyphs, "sa1").compartment_ref.as_deref(), Some("ca1"));
+        assert_eq!(find(&glyphs, "csa1").class_name, "complex");
+        for (member, class_name) in [("sa6", "macromolecule"), ("sa7", "simple chemical")] {
+            assert_eq!(find(&glyphs, member).class_name, class_name);
+            assert_eq!(find(&glyphs, member).parent_id.as_deref(), Some("csa1"));
+        }
+        assert!(find(&glyphs, "sa4").has_clone && find(&glyphs, "sa4b").has_clone);
+        assert!(!find(&glyphs, "sa1").has_clone);
+    }
+
+    #[test]
+    fn homodimers_become_multimers_with_named_state_variables() {
+        let doc = Document::parse(MAP).unwrap();
+        let (glyphs, _) = parse_celldesigner(&doc).unwrap();
+        assert_eq!(find(&glyphs, "sa2").class_name, "macromolecule multimer");
+        let unit = find(&glyphs, "sa2_multimer");
+        assert_eq!(unit.class_name, "unit of information");
+        assert_eq!(unit.label, "N:2");
+        assert_eq!(unit.parent_id.as_deref(), Some("sa2"));
+        let states: Vec<_> = glyphs
+            .iter()
+            .filter(|glyph| {
+                glyph.class_name == "state variable" && glyph.parent_id.as_deref() == Some("sa2")
+            })
+            .map(|glyph| {
+                (
+                    glyph.state_value.as_deref(),
+                    glyph.state_variable.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            states,
+            [(Some("P"), Some("S218")), (Some("P"), Some("S222"))]
+        );
+    }
+
+    #[test]
+    fn logic_gates_collect_their_inputs_and_modulate_the_process() {
+        let doc = Document::parse(MAP).unwrap();
+        let (glyphs, arcs) = parse_celldesigner(&doc).unwrap();
+        assert_eq!(find(&glyphs, "re1").class_name, "process");
+        assert_eq!(find(&glyphs, "re1_gate_0").class_name, "and");
+        let mut wiring: Vec<_> = arcs
+            .iter()
+            .map(|arc| {
+                (
+                    arc.class_name.as_str(),
+                    arc.source.as_deref().unwrap(),
+                    arc.target.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        wiring.sort();
+        // The plain catalysis by sa3 is carried by the gate and not drawn twice.
+        assert_eq!(
+            wiring,
+            [
+                ("catalysis", "re1_gate_0", "re1"),
+                ("consumption", "sa1", "re1"),
+                ("consumption", "sa4", "re1"),
+                ("logic arc", "csa1", "re1_gate_0"),
+                ("logic arc", "sa3", "re1_gate_0"),
+                ("production", "re1", "sa2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn species_classes_map_to_sbgn_classes() {
+        for (class, class_name) in [
+            ("PROTEIN", "macromolecule"),
+            ("RECEPTOR", "macromolecule"),
+            ("GENE", "nucleic acid feature"),
+            ("ION", "simple chemical"),
+            ("COMPLEX", "complex"),
+            ("PHENOTYPE", "phenotype"),
+            ("DEGRADED", "source and sink"),
+            ("UNKNOWN", "unspecified entity"),
+        ] {
+            assert_eq!(species_class(class), class_name);
+        }
+    }
+
+    #[test]
+    fn stray_celldesigner_annotation_is_not_a_celldesigner_map() {
+        let doc = Document::parse(
+            r#"<sbml xmlns="http://www.sbml.org/sbml/level3/version1/core" xmlns:celldesigner="http://www.sbml.org/2001/ns/celldesigner">
+<model id="m"><annotation><celldesigner:extension><celldesigner:modelVersion>4.0</celldesigner:modelVersion></celldesigner:extension></annotation></model>
+</sbml>"#,
+        )
+        .unwrap();
+        assert!(!is_celldesigner(&doc));
+        assert!(is_celldesigner(&Document::parse(MAP).unwrap()));
+    }
+
+    #[test]
+    fn reaction_arcs_run_straight_between_aliases_and_process() {
+        let doc = Document::parse(MODEL).unwrap();
+        let (_, arcs) = parse_celldesigner(&doc).unwrap();
+        let points = |class_name: &str| {
+            let arc = arcs
+                .iter()
+                .find(|arc| arc.class_name == class_name)
+                .unwrap();
+            arc.points
+                .iter()
+                .map(|point| (point.x, point.y))
+                .collect::<Vec<_>>()
+        };
+        // The process sits midway between the aliases; arcs meet its connectors.
+        assert_eq!(points("consumption"), [(100.0, 25.0), (177.0, 25.0)]);
+        assert_eq!(points("production"), [(223.0, 25.0), (300.0, 25.0)]);
+    }
+}
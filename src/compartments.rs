@@ -0,0 +1,163 @@
+//! Which compartment each glyph belongs to, for compartment-aware layout and for
+//! `--list-compartments`.
+
+use std::collections::HashMap;
+
+use crate::{is_aux_class, BBox, Glyph};
+
+/// Map each glyph id to the id of the compartment it belongs to.
+///
+/// Membership comes from `compartmentRef` first, then from XML nesting (a glyph inside a
+/// compartment, or inside a complex that sits in one). Glyphs with neither fall back to
+/// the smallest compartment whose bbox contains their center.
+pub(crate) fn compartment_membership(glyphs: &[Glyph]) -> HashMap<String, String> {
+    let by_id: HashMap<&str, &Glyph> = glyphs
+        .iter()
+        .map(|glyph| (glyph.id.as_str(), glyph))
+        .collect();
+    let compartments: Vec<&Glyph> = glyphs
+        .iter()
+        .filter(|glyph| glyph.class_name == "compartment")
+        .collect();
+
+    let mut membership = HashMap::new();
+    for glyph in glyphs {
+        let compartment = declared_compartment(glyph, &by_id)
+            .or_else(|| enclosing_compartment(glyph, &compartments));
+        if let Some(compartment) = compartment {
+            membership.insert(glyph.id.clone(), compartment.to_string());
+        }
+    }
+    membership
+}
+
+/// Compartments in file order, each with the non-auxiliary glyphs that belong to it.
+pub(crate) fn compartment_members(glyphs: &[Glyph]) -> Vec<(&Glyph, Vec<&Glyph>)> {
+    let membership = compartment_membership(glyphs);
+    glyphs
+        .iter()
+        .filter(|glyph| glyph.class_name == "compartment")
+        .map(|compartment| {
+            let members = glyphs
+                .iter()
+                .filter(|glyph| !is_aux_class(&glyph.class_name))
+                .filter(|glyph| membership.get(&glyph.id) == Some(&compartment.id))
+                .collect();
+            (compartment, members)
+        })
+        .collect()
+}
+
+/// Compartments and their members as tab-separated lines for `--list-compartments`.
+///
+/// A compartment line holds its id, label, order and enclosing compartment; each member
+/// follows on an indented line with its id, class and label.
+pub(crate) fn compartment_listing(glyphs: &[Glyph]) -> Vec<String> {
+    let membership = compartment_membership(glyphs);
+    let mut lines = Vec::new();
+    for (compartment, members) in compartment_members(glyphs) {
+        let order = compartment
+            .compartment_order
+            .map(|order| order.to_string())
+            .unwrap_or_default();
+        let outer = membership
+            .get(&compartment.id)
+            .map(String::as_str)
+            .unwrap_or("");
+        lines.push(format!(
+            "{}\t{}\t{}\t{}",
+            compartment.id,
+            one_line(&compartment.label),
+            order,
+            outer
+        ));
+        for member in members {
+            lines.push(format!(
+                "\t{}\t{}\t{}",
+                member.id,
+                member.class_name,
+                one_line(&member.label)
+            ));
+        }
+    }
+    lines
+}
+
+fn declared_compartment<'a>(glyph: &'a Glyph, by_id: &HashMap<&str, &'a Glyph>) -> Option<&'a str> {
+    if let Some(compartment_ref) = glyph.compartment_ref.as_deref() {
+        if by_id.contains_key(compartment_ref) && compartment_ref != glyph.id {
+            return Some(compartment_ref);
+        }
+    }
+    let mut current = glyph;
+    // Bounded by the glyph count so malformed parent chains cannot loop forever.
+    for _ in 0..by_id.len() {
+        let parent = by_id.get(current.parent_id.as_deref()?)?;
+        if parent.class_name == "compartment" {
+            return Some(parent.id.as_str());
+        }
+        if let Some(compartment_ref) = parent.compartment_ref.as_deref() {
+            return by_id
+                .contains_key(compartment_ref)
+                .then_some(compartment_ref);
+        }
+        current = parent;
+    }
+    None
+}
+
+fn enclosing_compartment<'a>(glyph: &Glyph, compartments: &[&'a Glyph]) -> Option<&'a str> {
+    let bbox = glyph.bbox?;
+    let center = (bbox.x + bbox.w / 2.0, bbox.y + bbox.h / 2.0);
+    compartments
+        .iter()
+        .filter(|compartment| compartment.id != glyph.id)
+        .filter_map(|compartment| Some((compartment, compartment.bbox?)))
+        .filter(|(_, outer)| contains_point(outer, center) && bbox_area(outer) > bbox_area(&bbox))
+        .min_by(|(_, a), (_, b)| bbox_area(a).total_cmp(&bbox_area(b)))
+        .map(|(compartment, _)| compartment.id.as_str())
+}
+
+fn contains_point(bbox: &BBox, (x, y): (f64, f64)) -> bool {
+    x >= bbox.x && x <= bbox.x + bbox.w && y >= bbox.y && y <= bbox.y + bbox.h
+}
+
+fn bbox_area(bbox: &BBox) -> f64 {
+    bbox.w * bbox.h
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(id: &str, class_name: &str, bbox: (f64, f64, f64, f64), label: &str) -> Glyph {
+        let (x, y, w, h) = bbox;
+        Glyph::new(id, class_name, Some(BBox { x, y, w, h }), label)
+    }
+
+    #[test]
+    fn listing_nests_members_under_their_compartment() {
+        let mut nucleus = glyph("n", "compartment", (10.0, 10.0, 50.0, 50.0), "nucleus");
+        nucleus.compartment_order = Some(2.0);
+        let glyphs = vec![
+            glyph("c", "compartment", (0.0, 0.0, 200.0, 100.0), "cyto\nplasm"),
+            nucleus,
+            glyph("p", "macromolecule", (20.0, 20.0, 20.0, 10.0), "P53"),
+            glyph("a", "simple chemical", (100.0, 20.0, 20.0, 20.0), "ATP"),
+        ];
+        assert_eq!(
+            compartment_listing(&glyphs),
+            [
+                "c\tcyto plasm\t\t",
+                "\tn\tcompartment\tnucleus",
+                "\ta\tsimple chemical\tATP",
+                "n\tnucleus\t2\tc",
+                "\tp\tmacromolecule\tP53",
+            ]
+        );
+    }
+}
@@ -0,0 +1,703 @@
+//! Graphviz DOT export, and import of `dot -Tplain` or `dot -Tjson` layouts.
+//!
+//! Glyphs become DOT nodes named by their id, and complexes or compartments with members become
+//! clusters named `cluster_<id>`. Arcs to a container point at one of its members and are clipped
+//! to the cluster with `lhead`/`ltail`. Imported positions are matched back by those names;
+//! edges are matched by their `id` attribute (json) or by tail and head in order (plain).
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crate::layout::{
+    arc_anchor, arc_edges, bbox_center, cubic_bezier, distance, glyph_index, is_aux,
+    is_process_like, layout_size, move_glyph, union_bbox, Containment, BEZIER_SAMPLES,
+    CONTAINER_LABEL_SPACE, CONTAINER_PADDING,
+};
+use crate::{Arc, BBox, Glyph, Point, LOGICAL_PORT_CONNECTOR_LEN_PX, PORT_CONNECTOR_LEN_PX};
+
+const POINTS_PER_INCH: f64 = 72.0;
+const CLUSTER_PREFIX: &str = "cluster_";
+
+/// Node positions, cluster boxes and edge splines read from Graphviz output, in SBGN units.
+struct DotLayout {
+    nodes: HashMap<String, BBox>,
+    clusters: HashMap<String, BBox>,
+    edges: Vec<DotEdge>,
+}
+
+struct DotEdge {
+    id: Option<String>,
+    tail: String,
+    head: String,
+    /// B-spline control points.
+    points: Vec<Point>,
+}
+
+/// Write the map graph as DOT, keeping glyph sizes and mapping SBGN classes to shapes and arrows.
+pub(crate) fn write_dot(path: &Path, glyphs: &[Glyph], arcs: &[Arc]) -> Result<()> {
+    fs::write(path, to_dot(glyphs, arcs)).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Lay out the map by piping it through the Graphviz `dot` binary.
+pub(crate) fn run_dot(glyphs: &mut [Glyph], arcs: &mut [Arc]) -> Result<()> {
+    let source = to_dot(glyphs, arcs);
+    let mut child = Command::new("dot")
+        .arg("-Tplain")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run Graphviz `dot`; is it installed and on PATH?")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin of `dot`")?
+        .write_all(source.as_bytes())
+        .context("Failed to send the map to `dot`")?;
+    let output = child
+        .wait_with_output()
+        .context("Failed to read the layout from `dot`")?;
+    if !output.status.success() {
+        bail!(
+            "Graphviz `dot` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let layout = parse_plain(&String::from_utf8_lossy(&output.stdout))?;
+    apply_dot_layout(glyphs, arcs, &layout);
+    Ok(())
+}
+
+/// Apply a layout saved from `dot -Tplain` or `dot -Tjson`.
+pub(crate) fn apply_layout_file(path: &Path, glyphs: &mut [Glyph], arcs: &mut [Arc]) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let layout = if text.trim_start().starts_with('{') {
+        parse_json_layout(&text)
+    } else {
+        parse_plain(&text)
+    }
+    .with_context(|| format!("Failed to parse Graphviz layout {:?}", path))?;
+    apply_dot_layout(glyphs, arcs, &layout);
+    Ok(())
+}
+
+fn to_dot(glyphs: &[Glyph], arcs: &[Arc]) -> String {
+    let index = glyph_index(glyphs);
+    let tree = Containment::new(glyphs, &index, &arc_edges(arcs, &index));
+
+    let mut out = String::from("digraph \"sbgn\" {\n");
+    out.push_str("  graph [rankdir=LR, compound=true, nodesep=0.4, ranksep=0.8];\n");
+    out.push_str("  node [fixedsize=true, fontsize=10];\n");
+    for &idx in &tree.roots {
+        write_member(&mut out, glyphs, &tree, idx, 1);
+    }
+    for (arc, ends) in arcs.iter().zip(dot_ends(glyphs, arcs, &index, &tree)) {
+        let Some(ends) = ends else {
+            continue;
+        };
+        let _ = write!(
+            out,
+            "  {} -> {} [id={}, arrowhead={}",
+            quote(&ends.tail),
+            quote(&ends.head),
+            quote(&arc.id),
+            dot_arrow(&arc.class_name)
+        );
+        if let Some(cluster) = &ends.tail_cluster {
+            let _ = write!(out, ", ltail={}", quote(cluster));
+        }
+        if let Some(cluster) = &ends.head_cluster {
+            let _ = write!(out, ", lhead={}", quote(cluster));
+        }
+        out.push_str("];\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn write_member(out: &mut String, glyphs: &[Glyph], tree: &Containment, idx: usize, depth: usize) {
+    let glyph = &glyphs[idx];
+    let indent = "  ".repeat(depth);
+    if tree.members(idx).is_empty() {
+        let (w, h) = layout_size(glyph);
+        let (shape, style) = dot_shape(&glyph.class_name);
+        let label = match glyph.class_name.as_str() {
+            "and" | "or" | "not" => glyph.class_name.to_uppercase(),
+            _ => glyph.label.clone(),
+        };
+        let _ = write!(
+            out,
+            "{indent}{} [label={}, shape={shape}, width={:.3}, height={:.3}",
+            quote(&glyph.id),
+            quote(&label),
+            w / POINTS_PER_INCH,
+            h / POINTS_PER_INCH
+        );
+        if let Some(style) = style {
+            let _ = write!(out, ", style={style}");
+        }
+        out.push_str("];\n");
+        return;
+    }
+    let _ = writeln!(
+        out,
+        "{indent}subgraph {} {{",
+        quote(&format!("{CLUSTER_PREFIX}{}", glyph.id))
+    );
+    let _ = writeln!(
+        out,
+        "{indent}  label={}; labelloc=b; margin={CONTAINER_PADDING};",
+        quote(&glyph.label)
+    );
+    for &member in tree.members(idx) {
+        write_member(out, glyphs, tree, member, depth + 1);
+    }
+    let _ = writeln!(out, "{indent}}}");
+}
+
+/// DOT node names an arc connects, with the clusters it should be clipped to.
+struct DotEnds {
+    tail: String,
+    head: String,
+    tail_cluster: Option<String>,
+    head_cluster: Option<String>,
+}
+
+fn dot_ends(
+    glyphs: &[Glyph],
+    arcs: &[Arc],
+    index: &HashMap<String, usize>,
+    tree: &Containment,
+) -> Vec<Option<DotEnds>> {
+    // Containers are clusters, so an arc to one is drawn to its first leaf member instead.
+    let end = |id: Option<&String>| -> Option<(String, Option<String>)> {
+        let idx = *index.get(id?.as_str())?;
+        if is_aux(&glyphs[idx]) {
+            return None;
+        }
+        let mut leaf = idx;
+        while let Some(&first) = tree.members(leaf).first() {
+            leaf = first;
+        }
+        let cluster = (leaf != idx).then(|| format!("{CLUSTER_PREFIX}{}", glyphs[idx].id));
+        Some((glyphs[leaf].id.clone(), cluster))
+    };
+    arcs.iter()
+        .map(|arc| {
+            let (tail, tail_cluster) = end(arc.source.as_ref())?;
+            let (head, head_cluster) = end(arc.target.as_ref())?;
+            Some(DotEnds {
+                tail,
+                head,
+                tail_cluster,
+                head_cluster,
+            })
+        })
+        .collect()
+}
+
+/// Move glyphs to their Graphviz positions and replace the paths of matching arcs.
+///
+/// Arcs touching a moved glyph that have no matching edge lose their points, so the regular
+/// layout pass routes them.
+fn apply_dot_layout(glyphs: &mut [Glyph], arcs: &mut [Arc], layout: &DotLayout) {
+    let index = glyph_index(glyphs);
+    let tree = Containment::new(glyphs, &index, &arc_edges(arcs, &index));
+    let ends = dot_ends(glyphs, arcs, &index, &tree);
+
+    let mut moved = vec![false; glyphs.len()];
+    for &idx in &tree.roots {
+        place_member(glyphs, &tree, layout, idx, &mut moved);
+    }
+
+    let mut by_id: HashMap<&str, &DotEdge> = HashMap::new();
+    let mut by_ends: HashMap<(&str, &str), VecDeque<&DotEdge>> = HashMap::new();
+    for edge in &layout.edges {
+        match edge.id.as_deref() {
+            Some(id) => {
+                by_id.insert(id, edge);
+            }
+            None => by_ends
+                .entry((edge.tail.as_str(), edge.head.as_str()))
+                .or_default()
+                .push_back(edge),
+        }
+    }
+    for (arc, ends) in arcs.iter_mut().zip(&ends) {
+        let edge = by_id.get(arc.id.as_str()).copied().or_else(|| {
+            let ends = ends.as_ref()?;
+            by_ends
+                .get_mut(&(ends.tail.as_str(), ends.head.as_str()))?
+                .pop_front()
+        });
+        let points = edge.map(|edge| flatten_spline(&edge.points));
+        let touches_moved = [arc.source.as_deref(), arc.target.as_deref()]
+            .into_iter()
+            .any(|id| {
+                id.and_then(|id| index.get(id))
+                    .is_some_and(|&idx| moved[idx])
+            });
+        match points {
+            Some(mut points) if points.len() >= 2 => {
+                // Graphviz stops splines short of arrowheads and cluster borders; reattach
+                // the ends to the glyphs, ports or process connectors they belong to.
+                points.reverse();
+                if let Some(source) = arc.source.as_deref() {
+                    attach_end(&mut points, glyphs, &index, source, &arc.class_name);
+                }
+                points.reverse();
+                if let Some(target) = arc.target.as_deref() {
+                    attach_end(&mut points, glyphs, &index, target, &arc.class_name);
+                }
+                arc.points = points;
+                arc.clear_glyph_positions();
+            }
+            _ if touches_moved => {
+                arc.points.clear();
+                arc.clear_glyph_positions();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace the last point of `points` with where the arc meets `id`, dropping spline points
+/// that reach closer to the glyph than that (connectors stick out past the node Graphviz saw).
+fn attach_end(
+    points: &mut Vec<Point>,
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    id: &str,
+    arc_class: &str,
+) {
+    let Some(center) = index
+        .get(id)
+        .and_then(|&idx| glyphs[idx].bbox)
+        .map(bbox_center)
+    else {
+        return;
+    };
+    let Some(anchor) = arc_anchor(glyphs, index, id, points[points.len() - 2], arc_class) else {
+        return;
+    };
+    let reach = distance(anchor, center);
+    points.pop();
+    while points.len() > 1 && distance(points[points.len() - 1], center) < reach {
+        points.pop();
+    }
+    points.push(anchor);
+}
+
+/// Place a glyph from the layout; containers are fitted to their cluster or their members.
+fn place_member(
+    glyphs: &mut [Glyph],
+    tree: &Containment,
+    layout: &DotLayout,
+    idx: usize,
+    moved: &mut [bool],
+) {
+    let members = tree.members(idx);
+    if members.is_empty() {
+        if let Some(&bbox) = layout.nodes.get(&glyphs[idx].id) {
+            move_glyph(glyphs, idx, bbox);
+            if is_process_like(&glyphs[idx].class_name) {
+                turn_horizontal(&mut glyphs[idx]);
+            }
+            moved[idx] = true;
+        }
+        return;
+    }
+    for &member in members {
+        place_member(glyphs, tree, layout, member, moved);
+    }
+    let cluster = layout.clusters.get(&glyphs[idx].id).copied();
+    let bbox = cluster.or_else(|| {
+        let boxes: Vec<BBox> = members
+            .iter()
+            .filter(|&&member| moved[member])
+            .filter_map(|&member| glyphs[member].bbox)
+            .collect();
+        union_bbox(&boxes).map(|inner| BBox {
+            x: inner.x - CONTAINER_PADDING,
+            y: inner.y - CONTAINER_PADDING,
+            w: inner.w + 2.0 * CONTAINER_PADDING,
+            h: inner.h + 2.0 * CONTAINER_PADDING + CONTAINER_LABEL_SPACE,
+        })
+    });
+    if let Some(bbox) = bbox {
+        move_glyph(glyphs, idx, bbox);
+        moved[idx] = true;
+    }
+}
+
+/// Graphviz ranks left to right, so processes take inputs on the left and outputs on the right.
+fn turn_horizontal(glyph: &mut Glyph) {
+    glyph.orientation = Some("horizontal".to_string());
+    let (Some(bbox), [first, second]) = (glyph.bbox, glyph.ports.as_mut_slice()) else {
+        return;
+    };
+    let connector_len = if matches!(glyph.class_name.as_str(), "and" | "or" | "not") {
+        LOGICAL_PORT_CONNECTOR_LEN_PX
+    } else {
+        PORT_CONNECTOR_LEN_PX
+    };
+    let center = bbox_center(bbox);
+    let reach = bbox.w / 2.0 + connector_len;
+    // Keep the stored port order: the port that came first along its axis becomes the left one.
+    let first_is_left = first.point.x + first.point.y <= second.point.x + second.point.y;
+    let (left, right) = if first_is_left {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    left.point = Point {
+        x: center.x - reach,
+        y: center.y,
+    };
+    right.point = Point {
+        x: center.x + reach,
+        y: center.y,
+    };
+}
+
+/// Sample a piecewise cubic B-spline given as Graphviz control points into a polyline.
+fn flatten_spline(control: &[Point]) -> Vec<Point> {
+    if control.len() < 4 || !(control.len() - 1).is_multiple_of(3) {
+        return control.to_vec();
+    }
+    let mut points = vec![control[0]];
+    for segment in control[1..].chunks(3) {
+        let start = *points.last().unwrap_or(&control[0]);
+        for step in 1..=BEZIER_SAMPLES {
+            let t = step as f64 / BEZIER_SAMPLES as f64;
+            points.push(cubic_bezier(start, segment[0], segment[1], segment[2], t));
+        }
+    }
+    points
+}
+
+/// Parse `dot -Tplain` output (inches, y axis pointing up).
+fn parse_plain(text: &str) -> Result<DotLayout> {
+    let mut layout = DotLayout {
+        nodes: HashMap::new(),
+        clusters: HashMap::new(),
+        edges: Vec::new(),
+    };
+    let mut height = None;
+    for line in text.lines() {
+        let tokens = plain_tokens(line);
+        let number = |i: usize| -> Result<f64> {
+            let token = tokens
+                .get(i)
+                .ok_or_else(|| anyhow!("Truncated line {:?}", line))?;
+            token
+                .parse::<f64>()
+                .with_context(|| format!("Invalid number {:?} in {:?}", token, line))
+        };
+        match tokens.first().map(String::as_str) {
+            Some("graph") => height = Some(number(3)?),
+            Some("node") => {
+                let graph_height = height.ok_or_else(|| anyhow!("Node before graph line"))?;
+                let (x, y, w, h) = (number(2)?, number(3)?, number(4)?, number(5)?);
+                layout.nodes.insert(
+                    tokens[1].clone(),
+                    BBox {
+                        x: (x - w / 2.0) * POINTS_PER_INCH,
+                        y: (graph_height - y - h / 2.0) * POINTS_PER_INCH,
+                        w: w * POINTS_PER_INCH,
+                        h: h * POINTS_PER_INCH,
+                    },
+                );
+            }
+            Some("edge") => {
+                let graph_height = height.ok_or_else(|| anyhow!("Edge before graph line"))?;
+                let count = number(3)? as usize;
+                let points = (0..count)
+                    .map(|i| {
+                        Ok(Point {
+                            x: number(4 + 2 * i)? * POINTS_PER_INCH,
+                            y: (graph_height - number(5 + 2 * i)?) * POINTS_PER_INCH,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                layout.edges.push(DotEdge {
+                    id: None,
+                    tail: tokens[1].clone(),
+                    head: tokens[2].clone(),
+                    points,
+                });
+            }
+            _ => {}
+        }
+    }
+    if height.is_none() {
+        bail!("No graph line in plain layout");
+    }
+    Ok(layout)
+}
+
+/// Split a plain-format line on whitespace, honoring double-quoted strings.
+fn plain_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut token = String::new();
+        if c == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => token.extend(chars.next()),
+                    c => token.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Parse `dot -Tjson` output (points, y axis pointing up).
+fn parse_json_layout(text: &str) -> Result<DotLayout> {
+    let root: Value = serde_json::from_str(text).context("Invalid JSON layout")?;
+    let bb = root
+        .get("bb")
+        .and_then(Value::as_str)
+        .and_then(parse_bb)
+        .ok_or_else(|| anyhow!("Layout has no graph bb"))?;
+    let height = bb.3;
+    let flip = |x: f64, y: f64| Point { x, y: height - y };
+
+    let mut layout = DotLayout {
+        nodes: HashMap::new(),
+        clusters: HashMap::new(),
+        edges: Vec::new(),
+    };
+    let mut names: HashMap<i64, String> = HashMap::new();
+    for object in items(&root, "objects") {
+        let Some(name) = object.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        if let Some(cluster) = name.strip_prefix(CLUSTER_PREFIX) {
+            if let Some((x0, y0, x1, y1)) =
+                object.get("bb").and_then(Value::as_str).and_then(parse_bb)
+            {
+                let top_left = flip(x0, y1);
+                layout.clusters.insert(
+                    cluster.to_string(),
+                    BBox {
+                        x: top_left.x,
+                        y: top_left.y,
+                        w: x1 - x0,
+                        h: y1 - y0,
+                    },
+                );
+            }
+            continue;
+        }
+        let pos = object
+            .get("pos")
+            .and_then(Value::as_str)
+            .and_then(parse_pair);
+        let w = object.get("width").and_then(number);
+        let h = object.get("height").and_then(number);
+        let (Some((x, y)), Some(w), Some(h)) = (pos, w, h) else {
+            continue;
+        };
+        let center = flip(x, y);
+        let (w, h) = (w * POINTS_PER_INCH, h * POINTS_PER_INCH);
+        layout.nodes.insert(
+            name.to_string(),
+            BBox {
+                x: center.x - w / 2.0,
+                y: center.y - h / 2.0,
+                w,
+                h,
+            },
+        );
+        if let Some(gvid) = object.get("_gvid").and_then(number) {
+            names.insert(gvid as i64, name.to_string());
+        }
+    }
+    for edge in items(&root, "edges") {
+        let name = |key: &str| {
+            let gvid = edge.get(key).and_then(number)? as i64;
+            names.get(&gvid).cloned()
+        };
+        let (Some(tail), Some(head)) = (name("tail"), name("head")) else {
+            continue;
+        };
+        let points = edge
+            .get("pos")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .split_whitespace()
+            // `s,x,y` and `e,x,y` are arrowhead tips, not control points.
+            .filter(|token| !token.starts_with("s,") && !token.starts_with("e,"))
+            .filter_map(parse_pair)
+            .map(|(x, y)| flip(x, y))
+            .collect();
+        layout.edges.push(DotEdge {
+            id: edge.get("id").and_then(Value::as_str).map(str::to_string),
+            tail,
+            head,
+            points,
+        });
+    }
+    Ok(layout)
+}
+
+fn items<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Graphviz writes most attributes as strings, so numeric strings count as numbers.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(text) => text.trim().parse().ok(),
+        value => value.as_f64(),
+    }
+}
+
+fn parse_pair(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = value.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn parse_bb(value: &str) -> Option<(f64, f64, f64, f64)> {
+    let parts: Vec<f64> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        &[x0, y0, x1, y1] => Some((x0, y0, x1, y1)),
+        _ => None,
+    }
+}
+
+/// Graphviz shape and style closest to an SBGN glyph class.
+fn dot_shape(class_name: &str) -> (&'static str, Option<&'static str>) {
+    match class_name {
+        "simple chemical" | "simple chemical multimer" | "unspecified entity" => ("ellipse", None),
+        "source and sink" | "and" | "or" | "not" | "equivalence" => ("circle", None),
+        "association" => ("circle", Some("filled")),
+        "dissociation" => ("doublecircle", None),
+        "process" | "omitted process" | "uncertain process" => ("square", None),
+        "complex" | "complex multimer" => ("octagon", None),
+        "phenotype" => ("hexagon", None),
+        "perturbing agent" => ("invtrapezium", None),
+        "tag" | "terminal" => ("cds", None),
+        "submap" | "nucleic acid feature" | "nucleic acid feature multimer" => ("box", None),
+        _ => ("box", Some("rounded")),
+    }
+}
+
+/// Graphviz arrowhead closest to the SBGN arc decoration.
+fn dot_arrow(class_name: &str) -> &'static str {
+    match class_name {
+        "consumption" | "logic arc" | "equivalence arc" => "none",
+        "catalysis" => "odot",
+        "inhibition" | "absolute inhibition" => "tee",
+        "modulation" => "odiamond",
+        "stimulation" | "absolute stimulation" => "empty",
+        "necessary stimulation" => "emptytee",
+        _ => "normal",
+    }
+}
+
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_layout_flips_y_and_converts_inches() {
+        let text = "graph 1 4 3\n\
+                    node g1 1 2.5 1 0.5 g1 solid box black lightgrey\n\
+                    node \"g \\\"2\\\"\" 3 0.5 0.5 0.5 x solid box black lightgrey\n\
+                    edge g1 \"g \\\"2\\\"\" 4 1 2.5 2 2 2.5 1 3 0.5 solid black\n\
+                    stop\n";
+        let layout = parse_plain(text).unwrap();
+        let g1 = layout.nodes["g1"];
+        assert_eq!((g1.x, g1.y, g1.w, g1.h), (36.0, 18.0, 72.0, 36.0));
+        assert!(layout.nodes.contains_key("g \"2\""));
+        let edge = &layout.edges[0];
+        assert_eq!((edge.tail.as_str(), edge.head.as_str()), ("g1", "g \"2\""));
+        assert_eq!(edge.points.len(), 4);
+        assert_eq!((edge.points[0].x, edge.points[0].y), (72.0, 36.0));
+        assert_eq!((edge.points[3].x, edge.points[3].y), (216.0, 180.0));
+    }
+
+    #[test]
+    fn plain_layout_needs_graph_line() {
+        assert!(parse_plain("node g1 1 1 1 1\n").is_err());
+        assert!(parse_plain("graph 1 4 x\n").is_err());
+    }
+
+    #[test]
+    fn json_layout_reads_nodes_clusters_and_edges() {
+        let text = r#"{
+            "name": "sbgn", "bb": "0,0,300,200",
+            "objects": [
+                {"_gvid": 0, "name": "cluster_c1", "bb": "10,20,110,180"},
+                {"_gvid": 1, "name": "gé \"1\"", "pos": "50,150", "width": "1",
+                 "height": 0.5},
+                {"_gvid": 2, "name": "g2", "pos": "2.5e2,50", "width": 0.5, "height": "0.5"}
+            ],
+            "edges": [
+                {"_gvid": 0, "tail": 1, "head": 2, "id": "a1",
+                 "pos": "e,240,55 60,140 100,120 180,80 230,60"}
+            ]
+        }"#;
+        let layout = parse_json_layout(text).unwrap();
+        let cluster = layout.clusters["c1"];
+        assert_eq!(
+            (cluster.x, cluster.y, cluster.w, cluster.h),
+            (10.0, 20.0, 100.0, 160.0)
+        );
+        let g1 = layout.nodes["gé \"1\""];
+        assert_eq!((g1.x, g1.y, g1.w, g1.h), (14.0, 32.0, 72.0, 36.0));
+        let g2 = layout.nodes["g2"];
+        assert_eq!((g2.x, g2.y), (232.0, 132.0));
+        let edge = &layout.edges[0];
+        assert_eq!(edge.id.as_deref(), Some("a1"));
+        assert_eq!((edge.tail.as_str(), edge.head.as_str()), ("gé \"1\"", "g2"));
+        assert_eq!(edge.points.len(), 4);
+        assert_eq!((edge.points[0].x, edge.points[0].y), (60.0, 60.0));
+    }
+
+    #[test]
+    fn json_layout_rejects_invalid_input() {
+        assert!(parse_json_layout("{\"bb\": \"0,0,1,1\"").is_err());
+        assert!(parse_json_layout("{\"objects\": []}").is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! SBGN-ML export of a laid-out map, keeping the input's unmodeled elements.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::layout::union_bbox;
+use crate::{parse_bbox, xml_escape, Arc, BBox, Glyph, Port};
+
+const SBGN_NS_03: &str = "http://sbgn.org/libsbgn/0.3";
+
+/// Document-level details of the map being exported, taken from the input when it was SBGN-ML.
+pub(crate) struct MapInfo {
+    namespace: String,
+    id: Option<String>,
+    /// Deprecated in 0.3 in favour of `version`, so only written when the input had it.
+    language: Option<String>,
+    version: Option<String>,
+    /// The input map's bbox; the written one also covers the laid-out glyphs.
+    bbox: Option<BBox>,
+    /// Prefixed namespaces in scope on the input map, so preserved fragments stay valid.
+    namespace_decls: Vec<(String, String)>,
+    /// Unmodeled children of `<sbgn>` (notes, extension).
+    root_extras: Vec<String>,
+    /// Unmodeled children of `<map>` (notes, extension).
+    map_extras: Vec<String>,
+    arcgroups: Vec<ArcGroup>,
+    /// Source of the document's other `<map>` elements (submaps), written back verbatim.
+    other_maps: Vec<String>,
+}
+
+/// Membership of an `<arcgroup>`; its glyphs and arcs are parsed with the top-level ones.
+struct ArcGroup {
+    class_name: String,
+    glyphs: Vec<String>,
+    arcs: Vec<String>,
+    extras: Vec<String>,
+}
+
+impl ArcGroup {
+    fn from_node(node: &roxmltree::Node) -> Self {
+        let ids = |tag: &str| {
+            node.children()
+                .filter(|child| child.has_tag_name(tag))
+                .filter_map(|child| child.attribute("id").map(str::to_string))
+                .collect()
+        };
+        Self {
+            class_name: node.attribute("class").unwrap_or_default().to_string(),
+            glyphs: ids("glyph"),
+            arcs: ids("arc"),
+            extras: crate::unmodeled_children(node, &["glyph", "arc"]),
+        }
+    }
+}
+
+impl MapInfo {
+    /// Map details for input that was not SBGN-ML (SBML, CellDesigner).
+    pub(crate) fn standalone() -> Self {
+        Self {
+            namespace: SBGN_NS_03.to_string(),
+            id: None,
+            language: Some("process description".to_string()),
+            version: None,
+            bbox: None,
+            namespace_decls: Vec::new(),
+            root_extras: Vec::new(),
+            map_extras: Vec::new(),
+            arcgroups: Vec::new(),
+            other_maps: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_map(map_node: &roxmltree::Node) -> Self {
+        let namespace = map_node
+            .tag_name()
+            .namespace()
+            .unwrap_or(SBGN_NS_03)
+            .to_string();
+        let namespace_decls = map_node
+            .namespaces()
+            .filter_map(|ns| Some((ns.name()?.to_string(), ns.uri().to_string())))
+            .filter(|(prefix, _)| prefix != "xml")
+            .collect();
+        let root = map_node.parent_element();
+        let root_extras = root
+            .map(|root| crate::unmodeled_children(&root, &["map"]))
+            .unwrap_or_default();
+        let other_maps = root
+            .into_iter()
+            .flat_map(|root| root.children())
+            .filter(|node| node.has_tag_name("map") && *node != *map_node)
+            .map(|node| map_node.document().input_text()[node.range()].to_string())
+            .collect();
+        let map_extras = crate::unmodeled_children(map_node, &["glyph", "arc", "arcgroup", "bbox"]);
+        let arcgroups = map_node
+            .children()
+            .filter(|node| node.has_tag_name("arcgroup"))
+            .map(|node| ArcGroup::from_node(&node))
+            .collect();
+        Self {
+            namespace,
+            id: map_node.attribute("id").map(str::to_string),
+            language: map_node.attribute("language").map(str::to_string),
+            version: map_node.attribute("version").map(str::to_string),
+            bbox: map_node
+                .children()
+                .find(|node| node.has_tag_name("bbox"))
+                .and_then(|node| parse_bbox(&node)),
+            namespace_decls,
+            root_extras,
+            map_extras,
+            arcgroups,
+            other_maps,
+        }
+    }
+}
+
+/// Write glyphs and arcs, with their current geometry, as an SBGN-ML document.
+pub(crate) fn write_sbgnml(
+    path: &Path,
+    info: &MapInfo,
+    glyphs: &[Glyph],
+    arcs: &[Arc],
+) -> Result<()> {
+    let mut children: HashMap<&str, Vec<&Glyph>> = HashMap::new();
+    for glyph in glyphs {
+        if let Some(parent_id) = glyph.parent_id.as_deref() {
+            children.entry(parent_id).or_default().push(glyph);
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    let _ = write!(out, "<sbgn xmlns=\"{}\"", xml_escape(&info.namespace));
+    for (prefix, uri) in &info.namespace_decls {
+        let _ = write!(out, " xmlns:{}=\"{}\"", prefix, xml_escape(uri));
+    }
+    out.push_str(">\n");
+    for extra in &info.root_extras {
+        let _ = writeln!(out, "  {extra}");
+    }
+    out.push_str("  <map");
+    if let Some(id) = &info.id {
+        let _ = write!(out, " id=\"{}\"", xml_escape(id));
+    }
+    if let Some(language) = &info.language {
+        let _ = write!(out, " language=\"{}\"", xml_escape(language));
+    }
+    if let Some(version) = &info.version {
+        let _ = write!(out, " version=\"{}\"", xml_escape(version));
+    }
+    out.push_str(">\n");
+    for extra in &info.map_extras {
+        let _ = writeln!(out, "    {extra}");
+    }
+    if let Some(map_bbox) = info.bbox {
+        let mut boxes = vec![map_bbox];
+        boxes.extend(glyphs.iter().filter_map(|glyph| glyph.bbox));
+        if let Some(bbox) = union_bbox(&boxes) {
+            let _ = writeln!(
+                out,
+                "    <bbox x=\"{}\" y=\"{}\" w=\"{}\" h=\"{}\"/>",
+                bbox.x, bbox.y, bbox.w, bbox.h
+            );
+        }
+    }
+    let grouped_glyphs: HashSet<&str> = info
+        .arcgroups
+        .iter()
+        .flat_map(|group| &group.glyphs)
+        .map(String::as_str)
+        .collect();
+    let grouped_arcs: HashSet<&str> = info
+        .arcgroups
+        .iter()
+        .flat_map(|group| &group.arcs)
+        .map(String::as_str)
+        .collect();
+    for glyph in glyphs
+        .iter()
+        .filter(|glyph| glyph.parent_id.is_none() && !grouped_glyphs.contains(glyph.id.as_str()))
+    {
+        write_glyph(&mut out, glyph, &children, 2);
+    }
+    for arc in arcs
+        .iter()
+        .filter(|arc| !grouped_arcs.contains(arc.id.as_str()))
+    {
+        write_arc(&mut out, arc, 2);
+    }
+    for group in &info.arcgroups {
+        let _ = writeln!(
+            out,
+            "    <arcgroup class=\"{}\">",
+            xml_escape(&group.class_name)
+        );
+        for extra in &group.extras {
+            let _ = writeln!(out, "      {extra}");
+        }
+        for id in &group.glyphs {
+            if let Some(glyph) = glyphs.iter().find(|glyph| &glyph.id == id) {
+                write_glyph(&mut out, glyph, &children, 3);
+            }
+        }
+        for id in &group.arcs {
+            if let Some(arc) = arcs.iter().find(|arc| &arc.id == id) {
+                write_arc(&mut out, arc, 3);
+            }
+        }
+        out.push_str("    </arcgroup>\n");
+    }
+    out.push_str("  </map>\n");
+    for other_map in &info.other_maps {
+        let _ = writeln!(out, "  {other_map}");
+    }
+    out.push_str("</sbgn>\n");
+
+    fs::write(path, out).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn write_glyph(
+    out: &mut String,
+    glyph: &Glyph,
+    children: &HashMap<&str, Vec<&Glyph>>,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    let _ = write!(
+        out,
+        "{indent}<glyph class=\"{}\" id=\"{}\"",
+        xml_escape(&glyph.class_name),
+        xml_escape(&glyph.id)
+    );
+    if let Some(orientation) = &glyph.orientation {
+        let _ = write!(out, " orientation=\"{}\"", xml_escape(orientation));
+    }
+    if let Some(compartment_ref) = &glyph.compartment_ref {
+        let _ = write!(out, " compartmentRef=\"{}\"", xml_escape(compartment_ref));
+    }
+    if let Some(order) = glyph.compartment_order {
+        let _ = write!(out, " compartmentOrder=\"{order}\"");
+    }
+    out.push_str(">\n");
+
+    // SBML and CellDesigner notes are plain text; wrap them the way SBGN-ML expects.
+    match (&glyph.notes_xml, &glyph.notes) {
+        (Some(notes_xml), _) => {
+            let _ = writeln!(out, "{indent}  {notes_xml}");
+        }
+        (None, Some(notes)) => {
+            let _ = writeln!(
+                out,
+                "{indent}  <notes><html xmlns=\"http://www.w3.org/1999/xhtml\"><body>{}</body></html></notes>",
+                xml_escape(notes)
+            );
+        }
+        (None, None) => {}
+    }
+    for extra in &glyph.extras {
+        let _ = writeln!(out, "{indent}  {extra}");
+    }
+    if let Some(annotation_xml) = &glyph.annotation_xml {
+        let _ = writeln!(out, "{indent}  {annotation_xml}");
+    }
+    if !glyph.label.is_empty() {
+        let _ = writeln!(
+            out,
+            "{indent}  <label text=\"{}\"/>",
+            text_attr(&glyph.label)
+        );
+    }
+    if glyph.state_value.is_some() || glyph.state_variable.is_some() {
+        let _ = write!(out, "{indent}  <state");
+        if let Some(value) = &glyph.state_value {
+            let _ = write!(out, " value=\"{}\"", xml_escape(value));
+        }
+        if let Some(variable) = &glyph.state_variable {
+            let _ = write!(out, " variable=\"{}\"", xml_escape(variable));
+        }
+        out.push_str("/>\n");
+    }
+    if glyph.has_clone {
+        match &glyph.clone_label {
+            Some(label) => {
+                let _ = writeln!(
+                    out,
+                    "{indent}  <clone><label text=\"{}\"/></clone>",
+                    text_attr(label)
+                );
+            }
+            None => {
+                let _ = writeln!(out, "{indent}  <clone/>");
+            }
+        }
+    }
+    if let Some(entity) = &glyph.entity {
+        let _ = writeln!(out, "{indent}  <entity name=\"{}\"/>", xml_escape(entity));
+    }
+    if let Some(bbox) = glyph.bbox {
+        let _ = writeln!(
+            out,
+            "{indent}  <bbox x=\"{}\" y=\"{}\" w=\"{}\" h=\"{}\"/>",
+            bbox.x, bbox.y, bbox.w, bbox.h
+        );
+    }
+    for child in children.get(glyph.id.as_str()).into_iter().flatten() {
+        write_glyph(out, child, children, depth + 1);
+    }
+    for port in &glyph.ports {
+        write_port(out, port, &indent);
+    }
+    let _ = writeln!(out, "{indent}</glyph>");
+}
+
+fn write_port(out: &mut String, port: &Port, indent: &str) {
+    let _ = writeln!(
+        out,
+        "{indent}  <port id=\"{}\" x=\"{}\" y=\"{}\"/>",
+        xml_escape(&port.id),
+        port.point.x,
+        port.point.y
+    );
+}
+
+fn write_arc(out: &mut String, arc: &Arc, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = write!(
+        out,
+        "{indent}<arc class=\"{}\" id=\"{}\"",
+        xml_escape(&arc.class_name),
+        xml_escape(&arc.id)
+    );
+    if let Some(source) = &arc.source {
+        let _ = write!(out, " source=\"{}\"", xml_escape(source));
+    }
+    if let Some(target) = &arc.target {
+        let _ = write!(out, " target=\"{}\"", xml_escape(target));
+    }
+    out.push_str(">\n");
+    for extra in &arc.extras {
+        let _ = writeln!(out, "{indent}  {extra}");
+    }
+    let no_children = HashMap::new();
+    for glyph in &arc.glyphs {
+        write_glyph(out, glyph, &no_children, depth + 1);
+    }
+    for port in &arc.ports {
+        write_port(out, port, &indent);
+    }
+    // start and end are required; an arc that was never routed keeps a zero-length path.
+    let points = match arc.points.as_slice() {
+        [] => vec![crate::Point { x: 0.0, y: 0.0 }; 2],
+        [only] => vec![*only, *only],
+        points => points.to_vec(),
+    };
+    let last = points.len() - 1;
+    for (index, point) in points.iter().enumerate() {
+        let tag = match index {
+            0 => "start",
+            index if index == last => "end",
+            _ => "next",
+        };
+        let _ = writeln!(
+            out,
+            "{indent}  <{tag} x=\"{}\" y=\"{}\"/>",
+            point.x, point.y
+        );
+    }
+    let _ = writeln!(out, "{indent}</arc>");
+}
+
+/// Escape label text for an attribute, keeping line breaks that attribute normalization drops.
+fn text_attr(value: &str) -> String {
+    xml_escape(value).replace('\n', "&#10;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const MAP: &str = r#"<sbgn xmlns="http://sbgn.org/libsbgn/0.3">
+  <map id="m" language="entity relationship">
+    <glyph class="entity" id="a">
+      <notes><html xmlns="http://www.w3.org/1999/xhtml"><body>Kinase</body></html></notes>
+      <label text="A"/>
+      <bbox x="0" y="0" w="40" h="20"/>
+    </glyph>
+    <glyph class="entity" id="b">
+      <label text="B"/>
+      <bbox x="100" y="0" w="40" h="20"/>
+    </glyph>
+    <arc class="modulation" id="ab" source="a" target="b">
+      <port id="ab.1" x="70" y="10"/>
+      <start x="40" y="10"/>
+      <end x="100" y="10"/>
+    </arc>
+    <arcgroup class="interaction">
+      <glyph class="interaction" id="i">
+        <bbox x="60" y="40" w="20" h="20"/>
+      </glyph>
+      <arc class="interaction" id="ai" source="i" target="a">
+        <start x="60" y="50"/>
+        <end x="20" y="20"/>
+      </arc>
+    </arcgroup>
+  </map>
+</sbgn>"#;
+
+    fn round_trip(source: &str) -> String {
+        let doc = roxmltree::Document::parse(source).unwrap();
+        let map_node = doc
+            .descendants()
+            .find(|node| node.has_tag_name("map"))
+            .unwrap();
+        let (glyphs, arcs) = crate::parse_map(&map_node).unwrap();
+        static RUN: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "export-test-{}-{}.sbgn",
+            std::process::id(),
+            RUN.fetch_add(1, Ordering::Relaxed)
+        ));
+        write_sbgnml(&path, &MapInfo::from_map(&map_node), &glyphs, &arcs).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        written
+    }
+
+    #[test]
+    fn round_trip_keeps_notes_ports_and_arcgroups() {
+        let written = round_trip(MAP);
+        assert_eq!(written.matches("<notes>").count(), 1);
+        assert!(written.contains("<port id=\"ab.1\" x=\"70\" y=\"10\"/>"));
+        assert_eq!(
+            written.matches("<arcgroup class=\"interaction\">").count(),
+            1
+        );
+
+        let doc = roxmltree::Document::parse(&written).unwrap();
+        let group = doc
+            .descendants()
+            .find(|node| node.has_tag_name("arcgroup"))
+            .unwrap();
+        let grouped: Vec<_> = group
+            .children()
+            .filter_map(|node| node.attribute("id"))
+            .collect();
+        assert_eq!(grouped, ["i", "ai"]);
+        let count = |tag: &str| {
+            doc.descendants()
+                .filter(|node| node.has_tag_name(tag))
+                .count()
+        };
+        assert_eq!(count("glyph"), 3);
+        assert_eq!(count("arc"), 2);
+    }
+
+    #[test]
+    fn round_trip_keeps_version_map_bbox_and_other_maps() {
+        let written = round_trip(
+            r#"<sbgn xmlns="http://sbgn.org/libsbgn/0.3">
+  <map id="top" version="http://identifiers.org/combine.specifications/sbgn.er.level-1.version-2">
+    <bbox x="0" y="0" w="500" h="300"/>
+    <glyph class="entity" id="a">
+      <bbox x="10" y="10" w="40" h="20"/>
+    </glyph>
+  </map>
+  <map id="sub" language="process description">
+    <glyph class="macromolecule" id="m"><label text="M"/></glyph>
+  </map>
+</sbgn>"#,
+        );
+        assert!(written.contains(
+            "<map id=\"top\" version=\"http://identifiers.org/combine.specifications/sbgn.er.level-1.version-2\">"
+        ));
+        assert!(!written.contains("<map id=\"top\" language"));
+        assert!(written.contains("<bbox x=\"0\" y=\"0\" w=\"500\" h=\"300\"/>"));
+        assert!(written.contains(
+            "<map id=\"sub\" language=\"process description\">\n    <glyph class=\"macromolecule\" id=\"m\"><label text=\"M\"/></glyph>\n  </map>"
+        ));
+    }
+
+    #[test]
+    fn round_trip_is_stable() {
+        let once = round_trip(MAP);
+        assert_eq!(round_trip(&once), once);
+    }
+}
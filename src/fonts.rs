@@ -0,0 +1,140 @@
+//! Label font and markup chosen once from the command line; the font optionally comes from
+//! a font file.
+//!
+//! Pango substitutes a fallback for a family it cannot find, which changes measured label
+//! widths and with them the size of aux units, so a missing family is reported.
+//!
+//! Font files are added through fontconfig, which is Pango's font backend on Linux and the
+//! BSDs only; elsewhere `--font-file` is an error and fonts must be installed.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use pango::prelude::*;
+use pango::FontDescription;
+
+use crate::markup::LabelMarkup;
+
+static FONT: OnceLock<Font> = OnceLock::new();
+
+pub(crate) const DEFAULT_FAMILY: &str = "Liberation Sans";
+
+/// The one fontconfig call Pango does not expose, kept behind a safe function.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod fontconfig {
+    use std::ffi::CStr;
+    use std::os::raw::{c_int, c_uchar, c_void};
+
+    #[link(name = "fontconfig")]
+    extern "C" {
+        /// Adds a font file to the application fonts of `config`, or of the current config
+        /// when null. Returns false when the file cannot be read as a font.
+        fn FcConfigAppFontAddFile(config: *mut c_void, file: *const c_uchar) -> c_int;
+    }
+
+    /// Add `file` to the application fonts of the current config; false if it is no font.
+    pub(super) fn app_font_add_file(file: &CStr) -> bool {
+        // SAFETY: a null config selects fontconfig's current config, which fontconfig creates
+        // and owns. `file` is a valid NUL-terminated string borrowed for the whole call, and
+        // fontconfig only reads it, copying the path before returning, so no pointer outlives
+        // the borrow. The call takes no other arguments and returns a plain FcBool.
+        unsafe { FcConfigAppFontAddFile(std::ptr::null_mut(), file.as_ptr().cast()) != 0 }
+    }
+}
+
+pub(crate) struct Font {
+    pub(crate) family: String,
+    /// Draw glyph outlines instead of text, so SVG and PDF need no font on the viewer's side.
+    pub(crate) text_as_paths: bool,
+    pub(crate) label_markup: LabelMarkup,
+}
+
+/// Make the fonts in a TTF/OTF file available to Pango for this run.
+///
+/// Must be called before the first layout is created, while the font map is still unbuilt.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn load_font_file(path: &Path) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // An interior NUL would silently truncate the path fontconfig sees.
+    let file = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| anyhow!("Font file path {:?} contains a NUL byte", path))?;
+    if !fontconfig::app_font_add_file(&file) {
+        return Err(anyhow!("Failed to load font file {:?}", path));
+    }
+    Ok(())
+}
+
+/// Without fontconfig there is no per-run font loading; the font has to be installed.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub(crate) fn load_font_file(path: &Path) -> Result<()> {
+    Err(anyhow!(
+        "--font-file {:?} is not supported on this platform; install the font and pass its \
+         family with --font",
+        path
+    ))
+}
+
+/// Names of the families Pango can use without substituting.
+pub(crate) fn available_families() -> Vec<String> {
+    pangocairo::FontMap::default()
+        .list_families()
+        .iter()
+        .map(|family| family.name().to_string())
+        .collect()
+}
+
+/// Install the font for this run; only the first call has an effect.
+///
+/// Warns when the family is not installed, since labels would then be measured and drawn with
+/// whatever Pango substitutes.
+pub(crate) fn set(font: Font) {
+    let families = available_families();
+    if !families
+        .iter()
+        .any(|family| family.eq_ignore_ascii_case(&font.family))
+    {
+        eprintln!(
+            "warning: font family {:?} is not installed, labels will use a substitute; \
+             pass --font-file or pick one of: {}",
+            font.family,
+            families.join(", ")
+        );
+    }
+    let _ = FONT.set(font);
+}
+
+pub(crate) fn current() -> &'static Font {
+    FONT.get_or_init(|| Font {
+        family: DEFAULT_FAMILY.to_string(),
+        text_as_paths: true,
+        label_markup: LabelMarkup::None,
+    })
+}
+
+/// The label font at `font_px` pixels.
+pub(crate) fn description(font_px: f64) -> FontDescription {
+    let mut font_desc = FontDescription::new();
+    font_desc.set_family(&current().family);
+    font_desc.set_absolute_size(font_px * pango::SCALE as f64);
+    font_desc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn font_file_path_with_nul_is_rejected() {
+        let error = load_font_file(Path::new("fonts\0.ttf")).unwrap_err();
+        assert!(error.to_string().contains("NUL byte"));
+    }
+
+    #[test]
+    fn missing_font_file_is_an_error() {
+        assert!(load_font_file(Path::new("/nonexistent/font.ttf")).is_err());
+    }
+}
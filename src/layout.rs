@@ -0,0 +1,918 @@
+//! Automatic layout for SBGN maps whose glyphs or arcs carry no coordinates.
+//!
+//! Glyphs are grouped by containment (complex children and `compartmentRef` members), each
+//! group is laid out as layered columns so processes sit between their reactants and products,
+//! and groups are sized bottom-up so every member stays inside its container.
+
+use std::collections::{HashMap, HashSet};
+
+use clap::ValueEnum;
+
+use crate::{
+    default_dimensions, Arc, BBox, Glyph, Point, LOGICAL_PORT_CONNECTOR_LEN_PX,
+    PORT_CONNECTOR_LEN_PX,
+};
+
+const LAYER_GAP: f64 = 80.0;
+const NODE_GAP: f64 = 40.0;
+pub(crate) const CONTAINER_PADDING: f64 = 20.0;
+pub(crate) const CONTAINER_LABEL_SPACE: f64 = 30.0;
+const LABEL_CHAR_WIDTH: f64 = 11.0;
+const FALLBACK_SIZE: (f64, f64) = (60.0, 40.0);
+const ORDERING_SWEEPS: usize = 4;
+const MAX_NUDGES: usize = 200;
+pub(crate) const BEZIER_SAMPLES: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LayoutMode {
+    /// Lay out every glyph and arc, ignoring stored coordinates.
+    Always,
+    /// Only fill in glyphs without a bbox and arcs without points.
+    Missing,
+    /// Render coordinates exactly as stored.
+    Never,
+    /// Lay out with the Graphviz `dot` binary on PATH, then fill in anything it left out.
+    Dot,
+}
+
+/// Fill in missing glyph bboxes and arc points before rendering.
+pub(crate) fn apply_layout(glyphs: &mut [Glyph], arcs: &mut [Arc], mode: LayoutMode) {
+    if mode == LayoutMode::Never {
+        return;
+    }
+    let index = glyph_index(glyphs);
+    let layout_glyphs = || glyphs.iter().filter(|glyph| !is_aux(glyph));
+    let any_placed = layout_glyphs().any(|glyph| glyph.bbox.is_some());
+    let any_missing = layout_glyphs().any(|glyph| glyph.bbox.is_none());
+    let full = mode == LayoutMode::Always || !any_placed;
+    if full {
+        if any_placed || any_missing {
+            layered_layout(glyphs, arcs, &index);
+        }
+    } else if any_missing {
+        place_missing(glyphs, arcs, &index);
+    }
+    route_arcs(glyphs, arcs, &index, full);
+}
+
+/// Map glyph ids and port ids to the index of the glyph that owns them.
+pub(crate) fn glyph_index(glyphs: &[Glyph]) -> HashMap<String, usize> {
+    let mut index = HashMap::new();
+    for (idx, glyph) in glyphs.iter().enumerate() {
+        if !glyph.id.is_empty() {
+            index.insert(glyph.id.clone(), idx);
+        }
+        for port in &glyph.ports {
+            if !port.id.is_empty() {
+                index.insert(port.id.clone(), idx);
+            }
+        }
+    }
+    index
+}
+
+/// Auxiliary units and submap terminals sit on their parent's border and move with it.
+pub(crate) fn is_aux(glyph: &Glyph) -> bool {
+    matches!(
+        glyph.class_name.as_str(),
+        "unit of information" | "state variable" | "existence" | "location" | "terminal"
+    )
+}
+
+pub(crate) fn is_container(glyph: &Glyph) -> bool {
+    matches!(
+        glyph.class_name.as_str(),
+        "compartment" | "complex" | "complex multimer"
+    )
+}
+
+pub(crate) fn is_process_like(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        "process"
+            | "omitted process"
+            | "uncertain process"
+            | "association"
+            | "dissociation"
+            | "and"
+            | "or"
+            | "not"
+            | "equivalence"
+    )
+}
+
+/// Return the glyph that visually contains `idx`: its XML parent, else its compartment.
+fn container_of(glyphs: &[Glyph], index: &HashMap<String, usize>, idx: usize) -> Option<usize> {
+    let glyph = &glyphs[idx];
+    if let Some(parent_id) = &glyph.parent_id {
+        return index.get(parent_id).copied();
+    }
+    let compartment = index.get(glyph.compartment_ref.as_deref()?).copied()?;
+    (compartment != idx && glyphs[compartment].class_name == "compartment").then_some(compartment)
+}
+
+/// Containment hierarchy of layout glyphs, with cyclic compartment references cut.
+pub(crate) struct Containment {
+    pub(crate) roots: Vec<usize>,
+    children: HashMap<usize, Vec<usize>>,
+    container: HashMap<usize, usize>,
+}
+
+impl Containment {
+    pub(crate) fn new(
+        glyphs: &[Glyph],
+        index: &HashMap<String, usize>,
+        edges: &[(usize, usize)],
+    ) -> Self {
+        let mut container = HashMap::new();
+        for idx in (0..glyphs.len()).filter(|&idx| !is_aux(&glyphs[idx])) {
+            if let Some(parent) = container_of(glyphs, index, idx) {
+                container.insert(idx, parent);
+            }
+        }
+        // Processes rarely carry a compartmentRef; keep them in the compartment their
+        // participants share so they land between reactants and products.
+        for idx in (0..glyphs.len()).filter(|&idx| is_process_like(&glyphs[idx].class_name)) {
+            if container.contains_key(&idx) {
+                continue;
+            }
+            let chains: Vec<Vec<usize>> = edges
+                .iter()
+                .filter_map(|&(source, target)| match (source == idx, target == idx) {
+                    (true, false) => Some(target),
+                    (false, true) => Some(source),
+                    _ => None,
+                })
+                .map(|neighbor| compartment_chain(glyphs, &container, neighbor))
+                .collect();
+            let shared = chains.split_first().and_then(|(first, rest)| {
+                first
+                    .iter()
+                    .copied()
+                    .find(|candidate| rest.iter().all(|chain| chain.contains(candidate)))
+            });
+            if let Some(compartment) = shared {
+                container.insert(idx, compartment);
+            }
+        }
+        // Drop any link that would make a glyph its own ancestor.
+        for idx in 0..glyphs.len() {
+            let mut seen = HashSet::from([idx]);
+            let mut current = idx;
+            while let Some(&parent) = container.get(&current) {
+                if !seen.insert(parent) {
+                    container.remove(&current);
+                    break;
+                }
+                current = parent;
+            }
+        }
+        let mut roots = Vec::new();
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for idx in (0..glyphs.len()).filter(|&idx| !is_aux(&glyphs[idx])) {
+            match container.get(&idx) {
+                Some(&parent) => children.entry(parent).or_default().push(idx),
+                None => roots.push(idx),
+            }
+        }
+        Self {
+            roots,
+            children,
+            container,
+        }
+    }
+
+    pub(crate) fn members(&self, idx: usize) -> &[usize] {
+        self.children
+            .get(&idx)
+            .map(|items| items.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn ancestors(&self, idx: usize) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut current = idx;
+        while let Some(&parent) = self.container.get(&current) {
+            result.push(parent);
+            current = parent;
+        }
+        result
+    }
+
+    /// Walk up from `idx` until reaching one of `members`.
+    fn lift(&self, idx: usize, members: &HashSet<usize>) -> Option<usize> {
+        let mut current = idx;
+        loop {
+            if members.contains(&current) {
+                return Some(current);
+            }
+            current = *self.container.get(&current)?;
+        }
+    }
+}
+
+/// List the compartments enclosing `idx`, innermost first.
+fn compartment_chain(
+    glyphs: &[Glyph],
+    container: &HashMap<usize, usize>,
+    idx: usize,
+) -> Vec<usize> {
+    let mut chain = Vec::new();
+    let mut current = idx;
+    while let Some(&parent) = container.get(&current) {
+        if chain.contains(&parent) || parent == idx {
+            break;
+        }
+        if glyphs[parent].class_name == "compartment" {
+            chain.push(parent);
+        }
+        current = parent;
+    }
+    chain
+}
+
+/// Resolve arcs to (source glyph, target glyph) pairs, ignoring unresolved ends.
+pub(crate) fn arc_edges(arcs: &[Arc], index: &HashMap<String, usize>) -> Vec<(usize, usize)> {
+    arcs.iter()
+        .filter_map(|arc| {
+            let source = *index.get(arc.source.as_deref()?)?;
+            let target = *index.get(arc.target.as_deref()?)?;
+            Some((source, target))
+        })
+        .collect()
+}
+
+/// Default size for a glyph placed by the layout, widened to fit its label.
+pub(crate) fn layout_size(glyph: &Glyph) -> (f64, f64) {
+    if let Some(bbox) = glyph.bbox {
+        return (bbox.w, bbox.h);
+    }
+    let (w, h) = default_dimensions(&glyph.class_name).unwrap_or(FALLBACK_SIZE);
+    if is_process_like(&glyph.class_name) {
+        return (w, h);
+    }
+    let label_w = glyph.label.chars().count() as f64 * LABEL_CHAR_WIDTH + 20.0;
+    (w.max(label_w), h)
+}
+
+fn layered_layout(glyphs: &mut [Glyph], arcs: &[Arc], index: &HashMap<String, usize>) {
+    let edges = arc_edges(arcs, index);
+    let tree = Containment::new(glyphs, index, &edges);
+    let mut sizes = HashMap::new();
+    let mut offsets = HashMap::new();
+    let roots = tree.roots.clone();
+    layout_group(&roots, &tree, &edges, glyphs, &mut sizes, &mut offsets);
+    place_group(
+        &roots,
+        Point { x: 0.0, y: 0.0 },
+        &tree,
+        &sizes,
+        &offsets,
+        glyphs,
+    );
+}
+
+/// Lay out `members` relative to their group origin, returning the group's content size.
+fn layout_group(
+    members: &[usize],
+    tree: &Containment,
+    edges: &[(usize, usize)],
+    glyphs: &[Glyph],
+    sizes: &mut HashMap<usize, (f64, f64)>,
+    offsets: &mut HashMap<usize, Point>,
+) -> (f64, f64) {
+    for &idx in members {
+        let glyph = &glyphs[idx];
+        let size = if !tree.members(idx).is_empty() {
+            let (inner_w, inner_h) =
+                layout_group(tree.members(idx), tree, edges, glyphs, sizes, offsets);
+            (
+                inner_w + 2.0 * CONTAINER_PADDING,
+                inner_h + 2.0 * CONTAINER_PADDING + CONTAINER_LABEL_SPACE,
+            )
+        } else {
+            layout_size(glyph)
+        };
+        sizes.insert(idx, size);
+    }
+
+    let member_set: HashSet<usize> = members.iter().copied().collect();
+    let mut group_edges: Vec<(usize, usize)> = edges
+        .iter()
+        .filter_map(|&(source, target)| {
+            let source = tree.lift(source, &member_set)?;
+            let target = tree.lift(target, &member_set)?;
+            (source != target).then_some((source, target))
+        })
+        .collect();
+    group_edges.sort_unstable();
+    group_edges.dedup();
+
+    let layers = order_layers(assign_layers(members, &group_edges), &group_edges);
+
+    let column_widths: Vec<f64> = layers
+        .iter()
+        .map(|layer| layer.iter().map(|idx| sizes[idx].0).fold(0.0, f64::max))
+        .collect();
+    let column_heights: Vec<f64> = layers
+        .iter()
+        .map(|layer| {
+            layer.iter().map(|idx| sizes[idx].1).sum::<f64>()
+                + NODE_GAP * layer.len().saturating_sub(1) as f64
+        })
+        .collect();
+    let height = column_heights.iter().copied().fold(0.0, f64::max);
+
+    let mut x = 0.0;
+    for ((layer, column_w), column_h) in layers.iter().zip(&column_widths).zip(&column_heights) {
+        let mut y = (height - column_h) / 2.0;
+        for idx in layer {
+            let (w, h) = sizes[idx];
+            offsets.insert(
+                *idx,
+                Point {
+                    x: x + (column_w - w) / 2.0,
+                    y,
+                },
+            );
+            y += h + NODE_GAP;
+        }
+        x += column_w + LAYER_GAP;
+    }
+    let width = (x - LAYER_GAP).max(0.0);
+    (width, height)
+}
+
+/// Longest-path layering over the acyclic part of the graph.
+fn assign_layers(members: &[usize], edges: &[(usize, usize)]) -> HashMap<usize, usize> {
+    let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(source, target) in edges {
+        successors.entry(source).or_default().push(target);
+    }
+
+    // Depth-first postorder gives a topological order once back edges are ignored.
+    let mut visited = HashSet::new();
+    let mut postorder = Vec::new();
+    for &start in members {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut stack = vec![(start, 0usize)];
+        while let Some((node, next_child)) = stack.pop() {
+            let next = successors
+                .get(&node)
+                .and_then(|items| items.get(next_child).copied());
+            match next {
+                Some(child) => {
+                    stack.push((node, next_child + 1));
+                    if visited.insert(child) {
+                        stack.push((child, 0));
+                    }
+                }
+                None => postorder.push(node),
+            }
+        }
+    }
+    let topo: Vec<usize> = postorder.into_iter().rev().collect();
+    let position: HashMap<usize, usize> = topo.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+    let forward: Vec<(usize, usize)> = edges
+        .iter()
+        .copied()
+        .filter(|(source, target)| position[source] < position[target])
+        .collect();
+
+    let mut layer: HashMap<usize, usize> = members.iter().map(|&idx| (idx, 0)).collect();
+    for &node in &topo {
+        for &(source, target) in forward.iter().filter(|(source, _)| *source == node) {
+            let candidate = layer[&source] + 1;
+            if candidate > layer[&target] {
+                layer.insert(target, candidate);
+            }
+        }
+    }
+
+    // Pull sources next to their first consumer so reactants sit just before their process.
+    let has_incoming: HashSet<usize> = forward.iter().map(|&(_, target)| target).collect();
+    for &node in topo.iter().rev() {
+        if has_incoming.contains(&node) {
+            continue;
+        }
+        let nearest = forward
+            .iter()
+            .filter(|(source, _)| *source == node)
+            .map(|(_, target)| layer[target])
+            .min();
+        if let Some(nearest) = nearest {
+            layer.insert(node, nearest.saturating_sub(1));
+        }
+    }
+    layer
+}
+
+/// Group nodes into layers and reduce crossings with barycenter sweeps.
+fn order_layers(layer: HashMap<usize, usize>, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let count = layer.values().copied().max().map_or(0, |max| max + 1);
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); count];
+    let mut nodes: Vec<(usize, usize)> = layer.into_iter().collect();
+    nodes.sort_unstable();
+    for (node, index) in nodes {
+        layers[index].push(node);
+    }
+
+    let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(source, target) in edges {
+        neighbors.entry(source).or_default().push(target);
+        neighbors.entry(target).or_default().push(source);
+    }
+
+    for sweep in 0..ORDERING_SWEEPS {
+        let downward = sweep % 2 == 0;
+        let indices: Vec<usize> = if downward {
+            (1..count).collect()
+        } else {
+            (0..count.saturating_sub(1)).rev().collect()
+        };
+        for index in indices {
+            let reference = if downward { index - 1 } else { index + 1 };
+            let rank: HashMap<usize, f64> = layers[reference]
+                .iter()
+                .enumerate()
+                .map(|(i, &node)| (node, i as f64))
+                .collect();
+            let mut keyed: Vec<(f64, usize)> = layers[index]
+                .iter()
+                .enumerate()
+                .map(|(i, &node)| {
+                    let ranks: Vec<f64> = neighbors
+                        .get(&node)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|other| rank.get(other).copied())
+                        .collect();
+                    let key = if ranks.is_empty() {
+                        i as f64
+                    } else {
+                        ranks.iter().sum::<f64>() / ranks.len() as f64
+                    };
+                    (key, node)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[index] = keyed.into_iter().map(|(_, node)| node).collect();
+        }
+    }
+    layers
+}
+
+/// Convert group-relative offsets into absolute bboxes, moving ports and aux units along.
+fn place_group(
+    members: &[usize],
+    origin: Point,
+    tree: &Containment,
+    sizes: &HashMap<usize, (f64, f64)>,
+    offsets: &HashMap<usize, Point>,
+    glyphs: &mut [Glyph],
+) {
+    for &idx in members {
+        let offset = offsets[&idx];
+        let (w, h) = sizes[&idx];
+        let bbox = BBox {
+            x: origin.x + offset.x,
+            y: origin.y + offset.y,
+            w,
+            h,
+        };
+        move_glyph(glyphs, idx, bbox);
+        let inner_origin = Point {
+            x: bbox.x + CONTAINER_PADDING,
+            y: bbox.y + CONTAINER_PADDING,
+        };
+        place_group(
+            tree.members(idx),
+            inner_origin,
+            tree,
+            sizes,
+            offsets,
+            glyphs,
+        );
+    }
+}
+
+/// Assign a new bbox to a glyph, translating its ports and aux units by the same offset.
+pub(crate) fn move_glyph(glyphs: &mut [Glyph], idx: usize, bbox: BBox) {
+    let previous = glyphs[idx].bbox.replace(bbox);
+    let Some(previous) = previous else {
+        // Stored ports are meaningless without the bbox they were relative to.
+        glyphs[idx].ports.clear();
+        return;
+    };
+    let dx = bbox.x - previous.x;
+    let dy = bbox.y - previous.y;
+    for port in &mut glyphs[idx].ports {
+        port.point.x += dx;
+        port.point.y += dy;
+    }
+    let parent_id = glyphs[idx].id.clone();
+    for glyph in glyphs.iter_mut() {
+        if is_aux(glyph) && glyph.parent_id.as_deref() == Some(parent_id.as_str()) {
+            if let Some(aux) = glyph.bbox.as_mut() {
+                aux.x += dx;
+                aux.y += dy;
+            }
+        }
+    }
+}
+
+/// Place glyphs without a bbox next to their neighbors or inside their container.
+fn place_missing(glyphs: &mut [Glyph], arcs: &[Arc], index: &HashMap<String, usize>) {
+    let edges = arc_edges(arcs, index);
+    let tree = Containment::new(glyphs, index, &edges);
+    let mut pending: Vec<usize> = (0..glyphs.len())
+        .filter(|&idx| !is_aux(&glyphs[idx]) && glyphs[idx].bbox.is_none())
+        .collect();
+    // Leaves first, then containers from the innermost outwards so they can wrap their members.
+    pending.sort_by_key(|&idx| {
+        (
+            is_container(&glyphs[idx]),
+            std::cmp::Reverse(tree.ancestors(idx).len()),
+        )
+    });
+
+    let mut row_cursor: Option<Point> = None;
+    for idx in pending {
+        if is_container(&glyphs[idx]) {
+            let members: Vec<BBox> = tree
+                .members(idx)
+                .iter()
+                .filter_map(|&member| glyphs[member].bbox)
+                .collect();
+            if let Some(bbox) = union_bbox(&members) {
+                let bbox = BBox {
+                    x: bbox.x - CONTAINER_PADDING,
+                    y: bbox.y - CONTAINER_PADDING,
+                    w: bbox.w + 2.0 * CONTAINER_PADDING,
+                    h: bbox.h + 2.0 * CONTAINER_PADDING + CONTAINER_LABEL_SPACE,
+                };
+                move_glyph(glyphs, idx, bbox);
+                continue;
+            }
+        }
+
+        let (w, h) = layout_size(&glyphs[idx]);
+        let container_bbox = tree
+            .container
+            .get(&idx)
+            .and_then(|&container| glyphs[container].bbox);
+        // Neighbors are shifted one layer gap so products land right of their source and
+        // reactants left of their target.
+        let neighbor_centers: Vec<Point> = edges
+            .iter()
+            .filter_map(|&(source, target)| match (source == idx, target == idx) {
+                (true, false) => glyphs[target].bbox.map(|bbox| (bbox, -1.0)),
+                (false, true) => glyphs[source].bbox.map(|bbox| (bbox, 1.0)),
+                _ => None,
+            })
+            .map(|(bbox, direction)| {
+                let center = bbox_center(bbox);
+                Point {
+                    x: center.x + direction * (bbox.w / 2.0 + LAYER_GAP + w / 2.0),
+                    y: center.y,
+                }
+            })
+            .collect();
+
+        let center = if !neighbor_centers.is_empty() {
+            let count = neighbor_centers.len() as f64;
+            Point {
+                x: neighbor_centers.iter().map(|p| p.x).sum::<f64>() / count,
+                y: neighbor_centers.iter().map(|p| p.y).sum::<f64>() / count,
+            }
+        } else if let Some(container) = container_bbox {
+            bbox_center(container)
+        } else {
+            // Unconnected glyphs go in a row below everything already placed.
+            let cursor = *row_cursor.get_or_insert_with(|| {
+                let placed: Vec<BBox> = glyphs.iter().filter_map(|glyph| glyph.bbox).collect();
+                let bounds = union_bbox(&placed).unwrap_or(BBox {
+                    x: 0.0,
+                    y: 0.0,
+                    w: 0.0,
+                    h: 0.0,
+                });
+                Point {
+                    x: bounds.x,
+                    y: bounds.y + bounds.h + NODE_GAP,
+                }
+            });
+            row_cursor = Some(Point {
+                x: cursor.x + w + NODE_GAP,
+                y: cursor.y,
+            });
+            Point {
+                x: cursor.x + w / 2.0,
+                y: cursor.y + h / 2.0,
+            }
+        };
+
+        let ancestors: HashSet<usize> = tree.ancestors(idx).into_iter().collect();
+        let obstacles: Vec<BBox> = glyphs
+            .iter()
+            .enumerate()
+            .filter(|(other, glyph)| !ancestors.contains(other) && !is_aux(glyph))
+            .filter_map(|(_, glyph)| glyph.bbox)
+            .collect();
+        let mut bbox = BBox {
+            x: center.x - w / 2.0,
+            y: center.y - h / 2.0,
+            w,
+            h,
+        };
+        if let Some(container) = container_bbox {
+            let max_x = container.x + container.w - CONTAINER_PADDING - w;
+            let max_y = container.y + container.h - CONTAINER_PADDING - h;
+            bbox.x = bbox.x.min(max_x).max(container.x + CONTAINER_PADDING);
+            bbox.y = bbox.y.min(max_y).max(container.y + CONTAINER_PADDING);
+        }
+        for _ in 0..MAX_NUDGES {
+            match obstacles.iter().find(|other| overlaps(&bbox, other)) {
+                Some(other) => bbox.y = other.y + other.h + NODE_GAP / 2.0,
+                None => break,
+            }
+        }
+        move_glyph(glyphs, idx, bbox);
+    }
+}
+
+/// Give arcs straight two-point paths between their (possibly newly placed) ends.
+fn route_arcs(glyphs: &[Glyph], arcs: &mut [Arc], index: &HashMap<String, usize>, all: bool) {
+    for arc in arcs.iter_mut() {
+        if !all && arc.points.len() >= 2 {
+            continue;
+        }
+        if let Some(points) = straight_path(glyphs, index, arc) {
+            arc.points = points;
+            arc.clear_glyph_positions();
+        }
+    }
+}
+
+/// Two-point path between where an arc meets its source and target, once both are placed.
+pub(crate) fn straight_path(
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    arc: &Arc,
+) -> Option<Vec<Point>> {
+    let source = arc.source.as_deref()?;
+    let target = arc.target.as_deref()?;
+    let source_center = endpoint_center(glyphs, index, source)?;
+    let target_center = endpoint_center(glyphs, index, target)?;
+    let start = arc_anchor(glyphs, index, source, target_center, &arc.class_name)?;
+    let end = arc_anchor(glyphs, index, target, source_center, &arc.class_name)?;
+    Some(vec![start, end])
+}
+
+pub(crate) fn endpoint_center(
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    id: &str,
+) -> Option<Point> {
+    let glyph = &glyphs[*index.get(id)?];
+    if let Some(port) = glyph.ports.iter().find(|port| port.id == id) {
+        return Some(port.point);
+    }
+    glyph.bbox.map(bbox_center)
+}
+
+/// Pick where an arc meets glyph or port `id`, given the point at the arc's other end.
+pub(crate) fn arc_anchor(
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    id: &str,
+    toward: Point,
+    arc_class: &str,
+) -> Option<Point> {
+    let glyph = &glyphs[*index.get(id)?];
+    if let Some(port) = glyph.ports.iter().find(|port| port.id == id) {
+        return Some(port.point);
+    }
+    let bbox = glyph.bbox?;
+    let center = bbox_center(bbox);
+    if !is_process_like(&glyph.class_name) {
+        return Some(bbox_boundary_point(bbox, toward));
+    }
+    let vertical = glyph.orientation.as_deref() == Some("vertical");
+    if !matches!(arc_class, "consumption" | "production" | "logic arc") {
+        // Modulators meet the process body on the sides without connectors.
+        return Some(if vertical {
+            let sign = if toward.x >= center.x { 1.0 } else { -1.0 };
+            Point {
+                x: center.x + sign * bbox.w / 2.0,
+                y: center.y,
+            }
+        } else {
+            let sign = if toward.y >= center.y { 1.0 } else { -1.0 };
+            Point {
+                x: center.x,
+                y: center.y + sign * bbox.h / 2.0,
+            }
+        });
+    }
+    let connector_len = if matches!(glyph.class_name.as_str(), "and" | "or" | "not") {
+        LOGICAL_PORT_CONNECTOR_LEN_PX
+    } else {
+        PORT_CONNECTOR_LEN_PX
+    };
+    Some(if vertical {
+        let sign = if toward.y >= center.y { 1.0 } else { -1.0 };
+        Point {
+            x: center.x,
+            y: center.y + sign * (bbox.h / 2.0 + connector_len),
+        }
+    } else {
+        let sign = if toward.x >= center.x { 1.0 } else { -1.0 };
+        Point {
+            x: center.x + sign * (bbox.w / 2.0 + connector_len),
+            y: center.y,
+        }
+    })
+}
+
+pub(crate) fn bbox_center(bbox: BBox) -> Point {
+    Point {
+        x: bbox.x + bbox.w / 2.0,
+        y: bbox.y + bbox.h / 2.0,
+    }
+}
+
+pub(crate) fn distance(a: Point, b: Point) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+pub(crate) fn cubic_bezier(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let u = 1.0 - t;
+    let blend = |a: f64, b: f64, c: f64, d: f64| {
+        u * u * u * a + 3.0 * u * u * t * b + 3.0 * u * t * t * c + t * t * t * d
+    };
+    Point {
+        x: blend(p0.x, p1.x, p2.x, p3.x),
+        y: blend(p0.y, p1.y, p2.y, p3.y),
+    }
+}
+
+/// Intersect the ray from the bbox center toward `toward` with the bbox border.
+fn bbox_boundary_point(bbox: BBox, toward: Point) -> Point {
+    let center = bbox_center(bbox);
+    let dx = toward.x - center.x;
+    let dy = toward.y - center.y;
+    if dx == 0.0 && dy == 0.0 {
+        return center;
+    }
+    let tx = if dx != 0.0 {
+        (bbox.w / 2.0) / dx.abs()
+    } else {
+        f64::INFINITY
+    };
+    let ty = if dy != 0.0 {
+        (bbox.h / 2.0) / dy.abs()
+    } else {
+        f64::INFINITY
+    };
+    let t = tx.min(ty);
+    Point {
+        x: center.x + dx * t,
+        y: center.y + dy * t,
+    }
+}
+
+fn overlaps(a: &BBox, b: &BBox) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+pub(crate) fn union_bbox(boxes: &[BBox]) -> Option<BBox> {
+    let first = boxes.first()?;
+    let (mut min_x, mut min_y) = (first.x, first.y);
+    let (mut max_x, mut max_y) = (first.x + first.w, first.y + first.h);
+    for bbox in &boxes[1..] {
+        min_x = min_x.min(bbox.x);
+        min_y = min_y.min(bbox.y);
+        max_x = max_x.max(bbox.x + bbox.w);
+        max_y = max_y.max(bbox.y + bbox.h);
+    }
+    Some(BBox {
+        x: min_x,
+        y: min_y,
+        w: max_x - min_x,
+        h: max_y - min_y,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(id: &str, class_name: &str, bbox: Option<(f64, f64, f64, f64)>) -> Glyph {
+        let bbox = bbox.map(|(x, y, w, h)| BBox { x, y, w, h });
+        Glyph::new(id, class_name, bbox, id)
+    }
+
+    fn arc(source: &str, target: &str) -> Arc {
+        Arc {
+            id: format!("{source}-{target}"),
+            class_name: "consumption".to_string(),
+            source: Some(source.to_string()),
+            target: Some(target.to_string()),
+            points: Vec::new(),
+            glyphs: Vec::new(),
+            ports: Vec::new(),
+            extras: Vec::new(),
+        }
+    }
+
+    fn bbox_of(glyphs: &[Glyph], id: &str) -> BBox {
+        glyphs
+            .iter()
+            .find(|glyph| glyph.id == id)
+            .unwrap()
+            .bbox
+            .unwrap()
+    }
+
+    #[test]
+    fn layers_follow_arcs_and_pull_sources_next_to_their_consumer() {
+        // 0 -> 1 -> 2 -> 3, with 4 feeding 3 directly.
+        let edges = [(0, 1), (1, 2), (2, 3), (4, 3)];
+        let layer = assign_layers(&[0, 1, 2, 3, 4], &edges);
+        assert_eq!(
+            (layer[&0], layer[&1], layer[&2], layer[&3], layer[&4]),
+            (0, 1, 2, 3, 2)
+        );
+    }
+
+    #[test]
+    fn layering_ignores_back_edges_of_cycles() {
+        let layer = assign_layers(&[0, 1, 2], &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!((layer[&0], layer[&1], layer[&2]), (0, 1, 2));
+    }
+
+    #[test]
+    fn ordering_uncrosses_edges_between_layers() {
+        let layer = HashMap::from([(0, 0), (1, 0), (2, 1), (3, 1)]);
+        let layers = order_layers(layer, &[(0, 3), (1, 2)]);
+        assert_eq!(layers, [vec![0, 1], vec![3, 2]]);
+    }
+
+    #[test]
+    fn missing_glyphs_go_beside_their_neighbors() {
+        let mut glyphs = vec![
+            glyph("a", "macromolecule", Some((0.0, 0.0, 60.0, 40.0))),
+            glyph("p", "process", None),
+        ];
+        let arcs = vec![arc("a", "p")];
+        let index = glyph_index(&glyphs);
+        place_missing(&mut glyphs, &arcs, &index);
+        let process = bbox_of(&glyphs, "p");
+        assert_eq!(bbox_center(process).y, 20.0);
+        assert_eq!(process.x, 60.0 + LAYER_GAP);
+    }
+
+    #[test]
+    fn missing_containers_wrap_their_members() {
+        let mut member = glyph("m", "macromolecule", Some((100.0, 50.0, 60.0, 40.0)));
+        member.parent_id = Some("c".to_string());
+        let mut glyphs = vec![glyph("c", "complex", None), member];
+        let index = glyph_index(&glyphs);
+        place_missing(&mut glyphs, &[], &index);
+        let complex = bbox_of(&glyphs, "c");
+        assert_eq!(
+            (complex.x, complex.y, complex.w, complex.h),
+            (
+                100.0 - CONTAINER_PADDING,
+                50.0 - CONTAINER_PADDING,
+                60.0 + 2.0 * CONTAINER_PADDING,
+                40.0 + 2.0 * CONTAINER_PADDING + CONTAINER_LABEL_SPACE
+            )
+        );
+    }
+
+    #[test]
+    fn unconnected_glyphs_go_in_a_row_below_the_map() {
+        let mut glyphs = vec![
+            glyph("a", "macromolecule", Some((0.0, 0.0, 60.0, 40.0))),
+            glyph("u", "macromolecule", None),
+            glyph("v", "macromolecule", None),
+        ];
+        let index = glyph_index(&glyphs);
+        place_missing(&mut glyphs, &[], &index);
+        let (u, v) = (bbox_of(&glyphs, "u"), bbox_of(&glyphs, "v"));
+        assert_eq!((u.x, u.y), (0.0, 40.0 + NODE_GAP));
+        assert_eq!(v.y, u.y);
+        assert!(v.x >= u.x + u.w);
+    }
+
+    #[test]
+    fn never_mode_keeps_missing_coordinates_missing() {
+        let mut glyphs = vec![glyph("a", "macromolecule", None)];
+        apply_layout(&mut glyphs, &mut [], LayoutMode::Never);
+        assert!(glyphs[0].bbox.is_none());
+    }
+}
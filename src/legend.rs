@@ -0,0 +1,367 @@
+//! A key to the glyph and arc classes used in a map, drawn with the map's own shapes.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cairo::Context as CairoContext;
+use clap::ValueEnum;
+
+use crate::overlay::Fills;
+use crate::{
+    create_png_surface, draw_arc, draw_text_left, measure_text_width, measuring_context,
+    render_glyph_tree, render_svg, set_color, theme, Arc, BBox, Glyph, Highlight, Point, Transform,
+    ARROW_SCALE, ARROW_SIZE, BAR_LENGTH, BAR_OFFSET, DEFAULT_LINE_WIDTH,
+};
+
+const LEGEND_PADDING_PX: f64 = 10.0;
+const ROW_HEIGHT_PX: f64 = 40.0;
+const SWATCH_WIDTH_PX: f64 = 72.0;
+const SWATCH_GAP_PX: f64 = 10.0;
+const COLUMN_GAP_PX: f64 = 20.0;
+const LEGEND_FONT_PX: f64 = 14.0;
+/// Rows per column when the legend is its own image.
+const SEPARATE_MAX_ROWS: usize = 12;
+
+/// Order of classes in the key; classes not listed follow in order of appearance.
+const GLYPH_CLASS_ORDER: &[&str] = &[
+    "unspecified entity",
+    "simple chemical",
+    "simple chemical multimer",
+    "macromolecule",
+    "macromolecule multimer",
+    "nucleic acid feature",
+    "nucleic acid feature multimer",
+    "complex",
+    "complex multimer",
+    "perturbing agent",
+    "source and sink",
+    "process",
+    "omitted process",
+    "uncertain process",
+    "association",
+    "dissociation",
+    "phenotype",
+    "and",
+    "or",
+    "not",
+    "equivalence",
+    "compartment",
+    "submap",
+    "tag",
+    "terminal",
+    "state variable",
+    "unit of information",
+];
+const ARC_CLASS_ORDER: &[&str] = &[
+    "consumption",
+    "production",
+    "modulation",
+    "stimulation",
+    "catalysis",
+    "inhibition",
+    "necessary stimulation",
+    "logic arc",
+    "equivalence arc",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LegendPosition {
+    /// Beside the map, growing the canvas to the right.
+    Right,
+    /// Below the map, growing the canvas downwards.
+    Bottom,
+    /// As its own `<output>_legend.png` and `.svg`.
+    Separate,
+}
+
+enum Entry {
+    Glyph(String),
+    Arc(String),
+}
+
+impl Entry {
+    fn class_name(&self) -> &str {
+        match self {
+            Entry::Glyph(class_name) | Entry::Arc(class_name) => class_name,
+        }
+    }
+}
+
+pub(crate) struct Legend {
+    position: LegendPosition,
+    entries: Vec<Entry>,
+    label_width: f64,
+    rows: usize,
+    columns: usize,
+}
+
+impl Legend {
+    /// Collect the classes used by the map and arrange them to fit beside or below it.
+    ///
+    /// Returns None for a map without glyphs or arcs to explain.
+    pub(crate) fn new(
+        glyphs: &[Glyph],
+        arcs: &[Arc],
+        position: LegendPosition,
+        map_width: f64,
+        map_height: f64,
+    ) -> Result<Option<Self>> {
+        let mut entries: Vec<Entry> = ordered_classes(
+            glyphs.iter().map(|glyph| glyph.class_name.as_str()),
+            GLYPH_CLASS_ORDER,
+        )
+        .into_iter()
+        .map(Entry::Glyph)
+        .collect();
+        entries.extend(
+            ordered_classes(
+                arcs.iter().map(|arc| arc.class_name.as_str()),
+                ARC_CLASS_ORDER,
+            )
+            .into_iter()
+            .map(Entry::Arc),
+        );
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        let ctx = measuring_context()?;
+        let label_width = entries
+            .iter()
+            .map(|entry| measure_text_width(&ctx, entry.class_name(), LEGEND_FONT_PX))
+            .fold(0.0, f64::max);
+
+        let count = entries.len();
+        let entry_width = SWATCH_WIDTH_PX + SWATCH_GAP_PX + label_width;
+        let (rows, columns) = match position {
+            LegendPosition::Right => {
+                let rows = ((map_height - 2.0 * LEGEND_PADDING_PX) / ROW_HEIGHT_PX)
+                    .floor()
+                    .max(1.0) as usize;
+                (rows.min(count), count.div_ceil(rows))
+            }
+            LegendPosition::Bottom => {
+                let columns = ((map_width - 2.0 * LEGEND_PADDING_PX + COLUMN_GAP_PX)
+                    / (entry_width + COLUMN_GAP_PX))
+                    .floor()
+                    .max(1.0) as usize;
+                let columns = columns.min(count);
+                (count.div_ceil(columns), columns)
+            }
+            LegendPosition::Separate => {
+                let columns = count.div_ceil(SEPARATE_MAX_ROWS);
+                (count.div_ceil(columns), columns)
+            }
+        };
+        Ok(Some(Self {
+            position,
+            entries,
+            label_width,
+            rows,
+            columns,
+        }))
+    }
+
+    pub(crate) fn size(&self) -> (f64, f64) {
+        let entry_width = SWATCH_WIDTH_PX + SWATCH_GAP_PX + self.label_width;
+        (
+            self.columns as f64 * entry_width
+                + (self.columns - 1) as f64 * COLUMN_GAP_PX
+                + 2.0 * LEGEND_PADDING_PX,
+            self.rows as f64 * ROW_HEIGHT_PX + 2.0 * LEGEND_PADDING_PX,
+        )
+    }
+
+    /// Canvas size for a map of the given size with this legend beside or below it.
+    pub(crate) fn page_size(&self, map_width: f64, map_height: f64) -> (f64, f64) {
+        let (width, height) = self.size();
+        match self.position {
+            LegendPosition::Right => (map_width + width, map_height.max(height)),
+            LegendPosition::Bottom => (map_width.max(width), map_height + height),
+            LegendPosition::Separate => (map_width, map_height),
+        }
+    }
+
+    /// Draw the legend beside or below a map drawn at the origin; no-op for separate legends.
+    pub(crate) fn draw_on_page(
+        &self,
+        ctx: &CairoContext,
+        map_width: f64,
+        map_height: f64,
+    ) -> Result<()> {
+        match self.position {
+            LegendPosition::Right => self.draw(
+                ctx,
+                Point {
+                    x: map_width,
+                    y: 0.0,
+                },
+            ),
+            LegendPosition::Bottom => self.draw(
+                ctx,
+                Point {
+                    x: 0.0,
+                    y: map_height,
+                },
+            ),
+            LegendPosition::Separate => Ok(()),
+        }
+    }
+
+    /// Write a separate legend next to `output` as `<stem>_legend.png` and `.svg`.
+    pub(crate) fn write_separate(&self, output: &Path) -> Result<()> {
+        if self.position != LegendPosition::Separate {
+            return Ok(());
+        }
+        let (width, height) = self.size();
+        let png_path = legend_output_path(output, "png");
+        let (surface, ctx) = create_png_surface(width.ceil() as i32, height.ceil() as i32)?;
+        self.draw(&ctx, Point { x: 0.0, y: 0.0 })?;
+        let mut file = fs::File::create(&png_path)
+            .with_context(|| format!("Failed to create {:?}", png_path))?;
+        surface
+            .write_to_png(&mut file)
+            .context("Failed to write PNG")?;
+        render_svg(&legend_output_path(output, "svg"), width, height, |ctx| {
+            self.draw(ctx, Point { x: 0.0, y: 0.0 })
+        })
+    }
+
+    fn draw(&self, ctx: &CairoContext, origin: Point) -> Result<()> {
+        let (width, height) = self.size();
+        ctx.rectangle(
+            origin.x + LEGEND_PADDING_PX / 2.0,
+            origin.y + LEGEND_PADDING_PX / 2.0,
+            width - LEGEND_PADDING_PX,
+            height - LEGEND_PADDING_PX,
+        );
+        set_color(ctx, theme::palette().border);
+        ctx.set_line_width(1.0);
+        ctx.stroke()?;
+        ctx.set_line_width(DEFAULT_LINE_WIDTH);
+
+        let entry_width = SWATCH_WIDTH_PX + SWATCH_GAP_PX + self.label_width;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let x = origin.x
+                + LEGEND_PADDING_PX
+                + (i / self.rows) as f64 * (entry_width + COLUMN_GAP_PX);
+            let y = origin.y + LEGEND_PADDING_PX + (i % self.rows) as f64 * ROW_HEIGHT_PX;
+            let center = Point {
+                x: x + SWATCH_WIDTH_PX / 2.0,
+                y: y + ROW_HEIGHT_PX / 2.0,
+            };
+            ctx.save()?;
+            ctx.rectangle(x, y, SWATCH_WIDTH_PX, ROW_HEIGHT_PX);
+            ctx.clip();
+            let swatch = match entry {
+                Entry::Glyph(class_name) => draw_glyph_swatch(ctx, class_name, center),
+                Entry::Arc(class_name) => draw_arc_swatch(ctx, class_name, center),
+            };
+            ctx.restore()?;
+            swatch?;
+            draw_text_left(
+                ctx,
+                x + SWATCH_WIDTH_PX + SWATCH_GAP_PX,
+                center.y,
+                entry.class_name(),
+                LEGEND_FONT_PX,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Distinct classes, known ones in `order` first and the rest in order of appearance.
+fn ordered_classes<'a>(classes: impl Iterator<Item = &'a str>, order: &[&str]) -> Vec<String> {
+    let mut seen: Vec<&str> = Vec::new();
+    for class_name in classes {
+        if !class_name.is_empty() && !seen.contains(&class_name) {
+            seen.push(class_name);
+        }
+    }
+    let rank = |class_name: &str| {
+        order
+            .iter()
+            .position(|known| *known == class_name)
+            .unwrap_or(order.len())
+    };
+    // Stable sort keeps unlisted classes in order of appearance.
+    seen.sort_by_key(|class_name| rank(class_name));
+    seen.into_iter().map(str::to_string).collect()
+}
+
+/// Swatch size for a class, leaving room for port connectors within the swatch.
+fn swatch_size(class_name: &str) -> (f64, f64) {
+    match class_name {
+        "process" | "omitted process" | "uncertain process" | "association" | "dissociation" => {
+            (20.0, 20.0)
+        }
+        "and" | "or" | "not" | "equivalence" => (28.0, 28.0),
+        "simple chemical" | "unspecified entity" | "source and sink" => (30.0, 30.0),
+        "simple chemical multimer" => (28.0, 28.0),
+        "state variable" | "unit of information" => (40.0, 18.0),
+        "existence" | "location" => (18.0, 18.0),
+        class_name if class_name.ends_with(" multimer") => (56.0, 28.0),
+        _ => (60.0, 30.0),
+    }
+}
+
+fn draw_glyph_swatch(ctx: &CairoContext, class_name: &str, center: Point) -> Result<()> {
+    let (w, h) = swatch_size(class_name);
+    let glyph = Glyph::new(
+        "",
+        class_name,
+        Some(BBox {
+            x: center.x - w / 2.0,
+            y: center.y - h / 2.0,
+            w,
+            h,
+        }),
+        "",
+    );
+    let identity = Transform::new(0.0, 0.0, 1.0, 1.0, 1.0, 1.0);
+    render_glyph_tree(
+        ctx,
+        &identity,
+        &glyph,
+        &HashMap::new(),
+        false,
+        &Highlight::none(),
+        &Fills::new(),
+        false,
+    )
+}
+
+fn draw_arc_swatch(ctx: &CairoContext, class_name: &str, center: Point) -> Result<()> {
+    let half = SWATCH_WIDTH_PX / 2.0 - 6.0;
+    let points = [
+        Point {
+            x: center.x - half,
+            y: center.y,
+        },
+        Point {
+            x: center.x + half,
+            y: center.y,
+        },
+    ];
+    draw_arc(
+        ctx,
+        &points,
+        class_name,
+        ARROW_SIZE * ARROW_SCALE,
+        BAR_LENGTH * ARROW_SCALE,
+        BAR_OFFSET * ARROW_SCALE,
+        theme::palette().border,
+        DEFAULT_LINE_WIDTH,
+    )
+}
+
+fn legend_output_path(output: &Path, extension: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "sbgnml".to_string());
+    output.with_file_name(format!("{stem}_legend.{extension}"))
+}
@@ -1,19 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use cairo::{Context as CairoContext, Format, ImageSurface, LineCap, SvgSurface};
-use clap::{Parser, Subcommand};
-use pango::{Alignment, FontDescription};
+use cairo::{
+    Context as CairoContext, Format, ImageSurface, LineCap, LineJoin, PdfSurface, SvgSurface,
+};
+use clap::{ArgAction, Parser, Subcommand};
+use pango::Alignment;
 use pangocairo::functions as pangocairo;
 use roxmltree::Document;
 
+mod animation;
+mod annotations;
+mod celldesigner;
+mod compartments;
+mod dot;
+mod export;
+mod fonts;
+mod layout;
+mod legend;
+mod markup;
+mod network;
+mod overlay;
+mod page;
+mod resolve;
+mod routing;
+mod sbml;
+mod theme;
+
+use annotations::Xref;
+use layout::LayoutMode;
+use legend::LegendPosition;
+use markup::LabelMarkup;
+use routing::RouteMode;
+use theme::{PaletteMode, ThemeName};
+
 const DEFAULT_PADDING_PX: f64 = 10.0;
 const DEFAULT_LINE_WIDTH: f64 = 1.5;
 const FONT_MAIN_PX: f64 = 20.0;
 const FONT_SMALL_PX: f64 = 12.0;
-const FONT_FAMILY: &str = "Liberation Sans";
 const TEXT_OUTLINE_WIDTH: f64 = 0.75;
 const ARROW_SIZE: f64 = 8.0;
 const ARROW_SCALE: f64 = 1.75;
@@ -21,32 +47,20 @@ const BAR_LENGTH: f64 = 12.0;
 const BAR_OFFSET: f64 = 14.0;
 const CATALYSIS_OVERLAP_RATIO: f64 = 0.5;
 const PORT_CONNECTOR_LEN_PX: f64 = 11.0;
+const ARC_LABEL_OFFSET_PX: f64 = 40.0;
 const LOGICAL_PORT_CONNECTOR_LEN_PX: f64 = 20.0;
 const SHOW_PROCESS_DEBUG: bool = false;
 const SHOW_LOGICAL_DEBUG_BBOX: bool = false;
-const BORDER_COLOR: (f64, f64, f64) = (
-    0x55 as f64 / 255.0,
-    0x55 as f64 / 255.0,
-    0x55 as f64 / 255.0,
-);
-const DEFAULT_FILL_COLOR: (f64, f64, f64) = (
-    0xF6 as f64 / 255.0,
-    0xF6 as f64 / 255.0,
-    0xF6 as f64 / 255.0,
-);
-const AUX_LINE_COLOR: (f64, f64, f64) = (
-    0x6A as f64 / 255.0,
-    0x6A as f64 / 255.0,
-    0x6A as f64 / 255.0,
-);
-const ASSOCIATION_FILL_COLOR: (f64, f64, f64) = (
-    0x6B as f64 / 255.0,
-    0x6B as f64 / 255.0,
-    0x6B as f64 / 255.0,
-);
 const CLONE_MARKER_HEIGHT_RATIO: f64 = 0.30;
-const CLONE_MARKER_FILL_COLOR: (f64, f64, f64) = (0.82, 0.82, 0.82);
 const CLONE_MARKER_STROKE_WIDTH: f64 = 1.5;
+const SUBMAP_BORDER_WIDTH: f64 = 3.0;
+const DEFAULT_HIGHLIGHT_COLOR: &str = "#E4572E";
+const COLORBLIND_HIGHLIGHT_COLOR: &str = "#CC79A7";
+const DEFAULT_DIM_OPACITY: f64 = 0.25;
+const HIGHLIGHT_LINE_WIDTH: f64 = 3.5;
+const HIGHLIGHT_GLOW_WIDTH: f64 = 12.0;
+const HIGHLIGHT_GLOW_ALPHA: f64 = 0.35;
+const DEFAULT_FRAME_DELAY_MS: u32 = 800;
 
 #[derive(Parser)]
 #[command(author, version, about = "Render SBGNML diagrams to PNG", long_about = None)]
@@ -67,6 +81,72 @@ enum Command {
         padding: f64,
         #[arg(long, default_value_t = true)]
         clone_markers: bool,
+        #[arg(long)]
+        highlight: Vec<String>,
+        #[arg(long)]
+        highlight_color: Option<String>,
+        #[arg(long, default_value_t = DEFAULT_DIM_OPACITY)]
+        dim_opacity: f64,
+        #[arg(long, value_enum, default_value_t = LayoutMode::Missing)]
+        layout: LayoutMode,
+        #[arg(long, value_enum, default_value_t = RouteMode::None)]
+        route: RouteMode,
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        clip_arcs: bool,
+        #[arg(long)]
+        submap_outputs: bool,
+        #[arg(long)]
+        list_compartments: bool,
+        #[arg(long)]
+        emit_sbgn: Option<PathBuf>,
+        #[arg(long)]
+        emit_cytoscape: Option<PathBuf>,
+        #[arg(long)]
+        emit_graphml: Option<PathBuf>,
+        #[arg(long)]
+        emit_dot: Option<PathBuf>,
+        #[arg(long)]
+        dot_layout: Option<PathBuf>,
+        #[arg(long)]
+        pdf_output: Option<PathBuf>,
+        #[arg(long)]
+        emit_annotations: Option<PathBuf>,
+        #[arg(long)]
+        synonyms: Option<PathBuf>,
+        #[arg(long)]
+        overlay: Option<PathBuf>,
+        #[arg(long)]
+        overlay_column: Option<String>,
+        #[arg(long)]
+        animation: Option<PathBuf>,
+        #[arg(long, default_value_t = DEFAULT_FRAME_DELAY_MS)]
+        frame_delay: u32,
+        #[arg(long, value_enum)]
+        legend: Option<LegendPosition>,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        caption: Option<String>,
+        #[arg(long)]
+        attribution: Option<String>,
+        #[arg(long)]
+        scale_bar: bool,
+        #[arg(long, value_enum, default_value_t = ThemeName::Light)]
+        theme: ThemeName,
+        #[arg(long)]
+        background: Option<String>,
+        #[arg(long, value_enum, default_value_t = PaletteMode::Color)]
+        palette: PaletteMode,
+        #[arg(long)]
+        arc_dashes: bool,
+        #[arg(long, default_value = fonts::DEFAULT_FAMILY)]
+        font: String,
+        #[arg(long)]
+        font_file: Vec<PathBuf>,
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        text_as_paths: bool,
+        #[arg(long, value_enum, default_value_t = LabelMarkup::None)]
+        label_markup: LabelMarkup,
     },
 }
 
@@ -100,17 +180,81 @@ struct Glyph {
     class_name: String,
     bbox: Option<BBox>,
     label: String,
-    ports: Vec<Point>,
+    ports: Vec<Port>,
     has_clone: bool,
+    clone_label: Option<String>,
+    entity: Option<String>,
     state_value: Option<String>,
     state_variable: Option<String>,
     orientation: Option<String>,
+    compartment_ref: Option<String>,
+    compartment_order: Option<f64>,
+    /// Fill styled by the input itself (SBML Render); overlay fills take precedence.
+    fill: Option<(f64, f64, f64)>,
+    notes: Option<String>,
+    /// Source of the `<notes>` element `notes` was read from, written back verbatim.
+    notes_xml: Option<String>,
+    xrefs: Vec<Xref>,
+    /// Source of a direct `<annotation>` child (SBGN-ML 0.3), written back verbatim.
+    annotation_xml: Option<String>,
+    /// Raw XML of child elements the renderer does not model (extension, ...).
+    extras: Vec<String>,
+}
+
+impl Glyph {
+    /// A top-level glyph with no ports, aux data or compartment.
+    fn new(id: &str, class_name: &str, bbox: Option<BBox>, label: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            parent_id: None,
+            class_name: class_name.to_string(),
+            bbox,
+            label: label.to_string(),
+            ports: Vec::new(),
+            has_clone: false,
+            clone_label: None,
+            entity: None,
+            state_value: None,
+            state_variable: None,
+            orientation: None,
+            compartment_ref: None,
+            compartment_order: None,
+            fill: None,
+            notes: None,
+            notes_xml: None,
+            xrefs: Vec::new(),
+            annotation_xml: None,
+            extras: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Port {
+    id: String,
+    point: Point,
 }
 
 #[derive(Debug)]
 struct Arc {
+    id: String,
     class_name: String,
+    source: Option<String>,
+    target: Option<String>,
     points: Vec<Point>,
+    glyphs: Vec<Glyph>,
+    ports: Vec<Port>,
+    /// Raw XML of child elements the renderer does not model (notes, extension, ...).
+    extras: Vec<String>,
+}
+
+impl Arc {
+    /// Forget stored positions of arc glyphs once the arc path has been recomputed.
+    fn clear_glyph_positions(&mut self) {
+        for glyph in &mut self.glyphs {
+            glyph.bbox = None;
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -143,6 +287,15 @@ impl Transform {
         }
     }
 
+    /// The same mapping with the drawing moved by `dx`, `dy` pixels.
+    fn offset(self, dx: f64, dy: f64) -> Self {
+        Self {
+            min_x: self.min_x - dx / self.scale_x,
+            min_y: self.min_y - dy / self.scale_y,
+            ..self
+        }
+    }
+
     fn map_point(&self, x: f64, y: f64) -> Point {
         Point {
             x: (x - self.min_x) * self.scale_x,
@@ -159,6 +312,71 @@ impl Transform {
     }
 }
 
+/// Glyph and arc ids drawn with an emphasis style while everything else is dimmed.
+///
+/// As parsed from the CLI the ids are user queries; `render_outputs` resolves them per map.
+struct Highlight {
+    ids: HashSet<String>,
+    color: (f64, f64, f64),
+    dim_opacity: f64,
+}
+
+impl Highlight {
+    fn none() -> Self {
+        Self {
+            ids: HashSet::new(),
+            color: (0.0, 0.0, 0.0),
+            dim_opacity: 1.0,
+        }
+    }
+
+    /// The same style applied to resolved glyph and arc ids.
+    fn with_ids(&self, ids: HashSet<String>) -> Self {
+        Self {
+            ids,
+            color: self.color,
+            dim_opacity: self.dim_opacity,
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        !id.is_empty() && self.ids.contains(id)
+    }
+
+    /// Opacity for an element that is not emphasized, or None when nothing is highlighted.
+    fn dim_alpha(&self, lit: bool) -> Option<f64> {
+        if self.ids.is_empty() || lit {
+            None
+        } else {
+            Some(self.dim_opacity)
+        }
+    }
+}
+
+/// Settings shared by every map rendered from one `draw_sbgnml` invocation.
+struct DrawOptions {
+    padding: f64,
+    show_clone_markers: bool,
+    highlight: Highlight,
+    layout_mode: LayoutMode,
+    route_mode: RouteMode,
+    clip_arcs: bool,
+    submap_outputs: bool,
+    emit_sbgn: Option<PathBuf>,
+    emit_cytoscape: Option<PathBuf>,
+    emit_graphml: Option<PathBuf>,
+    emit_dot: Option<PathBuf>,
+    dot_layout: Option<PathBuf>,
+    pdf_output: Option<PathBuf>,
+    emit_annotations: Option<PathBuf>,
+    synonyms: resolve::Synonyms,
+    overlay: Option<overlay::Overlay>,
+    animation: Option<PathBuf>,
+    frame_delay: u32,
+    legend: Option<LegendPosition>,
+    page_text: page::PageText,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
@@ -167,17 +385,152 @@ fn main() -> Result<()> {
             output,
             padding,
             clone_markers,
+            highlight,
+            highlight_color,
+            dim_opacity,
+            layout,
+            route,
+            clip_arcs,
+            submap_outputs,
+            list_compartments,
+            emit_sbgn,
+            emit_cytoscape,
+            emit_graphml,
+            emit_dot,
+            dot_layout,
+            pdf_output,
+            emit_annotations,
+            synonyms,
+            overlay,
+            overlay_column,
+            animation,
+            frame_delay,
+            legend,
+            title,
+            caption,
+            attribution,
+            scale_bar,
+            theme,
+            background,
+            palette,
+            arc_dashes,
+            font,
+            font_file,
+            text_as_paths,
+            label_markup,
         } => {
+            if list_compartments {
+                return list_sbgnml_compartments(&input);
+            }
+            if animation.is_some() && overlay.is_none() {
+                return Err(anyhow!("--animation needs an --overlay table"));
+            }
+            let background = background
+                .as_deref()
+                .map(theme::Background::parse)
+                .transpose()?;
+            theme::set(theme::Theme::new(theme, palette, background, arc_dashes));
+            for path in &font_file {
+                fonts::load_font_file(path)?;
+            }
+            fonts::set(fonts::Font {
+                family: font,
+                text_as_paths,
+                label_markup,
+            });
             let svg_path = default_svg_output_path(&output);
-            draw_sbgnml(&input, &output, padding, &svg_path, clone_markers)
+            let highlight = if highlight.is_empty() {
+                Highlight::none()
+            } else {
+                Highlight {
+                    ids: load_highlight_ids(&highlight)?,
+                    color: theme::current().adjust(parse_hex_color(
+                        highlight_color.as_deref().unwrap_or(match palette {
+                            PaletteMode::Colorblind => COLORBLIND_HIGHLIGHT_COLOR,
+                            _ => DEFAULT_HIGHLIGHT_COLOR,
+                        }),
+                    )?),
+                    dim_opacity: dim_opacity.clamp(0.0, 1.0),
+                }
+            };
+            let options = DrawOptions {
+                padding,
+                show_clone_markers: clone_markers,
+                highlight,
+                layout_mode: layout,
+                route_mode: route,
+                clip_arcs,
+                submap_outputs,
+                emit_sbgn,
+                emit_cytoscape,
+                emit_graphml,
+                emit_dot,
+                dot_layout,
+                pdf_output,
+                emit_annotations,
+                synonyms: match synonyms {
+                    Some(path) => resolve::Synonyms::load(&path)?,
+                    None => resolve::Synonyms::none(),
+                },
+                overlay: overlay
+                    .map(|path| overlay::Overlay::load(&path, overlay_column.as_deref()))
+                    .transpose()?,
+                animation,
+                frame_delay,
+                legend,
+                page_text: page::PageText {
+                    title,
+                    caption,
+                    attribution,
+                    scale_bar,
+                },
+            };
+            draw_sbgnml(&input, &output, &svg_path, &options)
+        }
+    }
+}
+
+/// Collect highlight queries from the CLI, expanding values that name a file into one per line.
+fn load_highlight_ids(values: &[String]) -> Result<HashSet<String>> {
+    let mut ids = HashSet::new();
+    for value in values {
+        let path = Path::new(value);
+        if path.is_file() {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read highlight ids from {:?}", path))?;
+            ids.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        } else {
+            ids.insert(value.trim().to_string());
         }
     }
+    Ok(ids)
+}
+
+fn set_color(ctx: &CairoContext, color: (f64, f64, f64)) {
+    ctx.set_source_rgb(color.0, color.1, color.2);
+}
+
+/// Parse a `#rrggbb` color into Cairo RGB components.
+fn parse_hex_color(value: &str) -> Result<(f64, f64, f64)> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid color {:?}, expected #rrggbb", value));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map(|v| v as f64 / 255.0);
+    Ok((channel(0)?, channel(2)?, channel(4)?))
 }
 
 fn setup_context(ctx: &CairoContext) -> Result<()> {
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
-    ctx.paint()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    if let theme::Background::Color(color) = theme::current().background {
+        ctx.set_source_rgb(color.0, color.1, color.2);
+        ctx.paint()?;
+    }
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     ctx.set_line_cap(LineCap::Square);
     Ok(())
@@ -191,6 +544,13 @@ fn create_png_surface(width: i32, height: i32) -> Result<(ImageSurface, CairoCon
     Ok((surface, ctx))
 }
 
+/// A scratch context for measuring text before the output surface exists.
+fn measuring_context() -> Result<CairoContext> {
+    let surface =
+        ImageSurface::create(Format::ARgb32, 1, 1).context("Failed to create image surface")?;
+    CairoContext::new(&surface).context("Failed to create Cairo context")
+}
+
 fn default_svg_output_path(output: &Path) -> PathBuf {
     let mut svg_path = output.to_path_buf();
     svg_path.set_extension("svg");
@@ -210,32 +570,356 @@ where
     Ok(())
 }
 
+fn render_pdf<F>(pdf_path: &Path, width: f64, height: f64, render: F) -> Result<()>
+where
+    F: FnOnce(&CairoContext) -> Result<()>,
+{
+    let surface =
+        PdfSurface::new(width, height, pdf_path).context("Failed to create PDF surface")?;
+    let ctx = CairoContext::new(&surface).context("Failed to create Cairo context")?;
+    setup_context(&ctx)?;
+    render(&ctx)?;
+    surface.finish();
+    Ok(())
+}
+
 fn draw_sbgnml(
     input: &Path,
     output: &Path,
-    padding: f64,
     svg_output: &Path,
-    show_clone_markers: bool,
+    options: &DrawOptions,
 ) -> Result<()> {
     let xml = fs::read_to_string(input).with_context(|| format!("Failed to read {:?}", input))?;
     let doc = Document::parse(&xml).context("Failed to parse SBGN XML")?;
-    let (glyphs, arcs, bounds) = parse_sbgn(&doc)?;
+    if sbml::is_sbml(&doc) {
+        let (mut glyphs, mut arcs) = parse_sbml_document(&doc)?;
+        let transform = render_outputs(&mut glyphs, &mut arcs, output, svg_output, true, options)?;
+        if let Some(path) = &options.emit_sbgn {
+            export::write_sbgnml(path, &export::MapInfo::standalone(), &glyphs, &arcs)?;
+        }
+        return write_exports(&glyphs, &arcs, &transform, options);
+    }
+    let map_node = find_map_node(&doc)?;
+    let mut rendered = HashSet::new();
+    draw_map(&doc, map_node, output, svg_output, options, &mut rendered)
+}
+
+/// Parse SBML input, preferring CellDesigner's layout extension when it is present.
+fn parse_sbml_document(doc: &Document) -> Result<(Vec<Glyph>, Vec<Arc>)> {
+    if celldesigner::is_celldesigner(doc) {
+        celldesigner::parse_celldesigner(doc)
+    } else {
+        sbml::parse_sbml(doc)
+    }
+}
+
+/// Print the compartments of the first map with their member glyphs.
+fn list_sbgnml_compartments(input: &Path) -> Result<()> {
+    let xml = fs::read_to_string(input).with_context(|| format!("Failed to read {:?}", input))?;
+    let doc = Document::parse(&xml).context("Failed to parse SBGN XML")?;
+    let (glyphs, _) = if sbml::is_sbml(&doc) {
+        parse_sbml_document(&doc)?
+    } else {
+        parse_map(&find_map_node(&doc)?)?
+    };
+    for line in compartments::compartment_listing(&glyphs) {
+        println!("{line}");
+    }
+    Ok(())
+}
 
-    let (transform, width_f, height_f) = transform_with_padding(bounds, padding);
-    let (surface, ctx) = create_png_surface(width_f.ceil() as i32, height_f.ceil() as i32)?;
-    render_sbgnml(&ctx, &transform, &glyphs, &arcs, show_clone_markers)?;
+/// Lay out, route and clip a parsed map, then write its PNG, SVG and optional PDF.
+///
+/// Only the top-level map gets the PDF and reports highlight queries it could not resolve.
+fn render_outputs(
+    glyphs: &mut [Glyph],
+    arcs: &mut [Arc],
+    output: &Path,
+    svg_output: &Path,
+    top_level: bool,
+    options: &DrawOptions,
+) -> Result<Transform> {
+    if let Some(path) = &options.dot_layout {
+        dot::apply_layout_file(path, glyphs, arcs)?;
+    } else if options.layout_mode == LayoutMode::Dot {
+        dot::run_dot(glyphs, arcs)?;
+    }
+    layout::apply_layout(glyphs, arcs, options.layout_mode);
+    routing::route_arcs(glyphs, arcs, options.route_mode)?;
+    // Routed arcs are already clipped to their end glyphs.
+    if options.clip_arcs && options.route_mode == RouteMode::None {
+        routing::clip_arcs(glyphs, arcs)?;
+    }
+    let bounds = compute_bounds(glyphs, arcs)?;
+    let resolution = resolve::resolve(&options.highlight.ids, glyphs, arcs, &options.synonyms);
+    if top_level {
+        resolution.report("--highlight");
+    }
+    let highlight = options.highlight.with_ids(resolution.ids);
+    let row_ids = options
+        .overlay
+        .as_ref()
+        .map(|overlay| overlay.resolve(glyphs, arcs, &options.synonyms, top_level));
+    let fills = match (&options.overlay, &row_ids) {
+        (Some(overlay), Some(row_ids)) => overlay.fills(overlay.column, row_ids),
+        _ => overlay::Fills::new(),
+    };
+
+    let (transform, width_f, height_f) = transform_with_padding(bounds, options.padding);
+    let legend = match options.legend {
+        Some(position) => legend::Legend::new(glyphs, arcs, position, width_f, height_f)?,
+        None => None,
+    };
+    let (page_width, page_height) = legend.as_ref().map_or((width_f, height_f), |legend| {
+        legend.page_size(width_f, height_f)
+    });
+    // Page text and the scale bar belong to the map named by --input, not its submaps.
+    let no_page_text = page::PageText::none();
+    let page_text = if top_level {
+        &options.page_text
+    } else {
+        &no_page_text
+    };
+    let (top_band, bottom_band) = page_text.bands(page_width)?;
+    let page_height = page_height + top_band + bottom_band;
+    let transform = transform.offset(0.0, top_band);
+    let glyphs: &[Glyph] = glyphs;
+    let arcs: &[Arc] = arcs;
+    // The map sits below the title band; a legend beside or below it extends the page.
+    let draw_page = |ctx: &CairoContext, fills: &overlay::Fills| -> Result<()> {
+        render_sbgnml(
+            ctx,
+            &transform,
+            glyphs,
+            arcs,
+            options.show_clone_markers,
+            &highlight,
+            fills,
+        )?;
+        if let Some(legend) = &legend {
+            ctx.save()?;
+            ctx.translate(0.0, top_band);
+            let drawn = legend.draw_on_page(ctx, width_f, height_f);
+            ctx.restore()?;
+            drawn?;
+        }
+        page_text.draw(ctx, page_width, page_height, transform.scale_scalar(1.0))
+    };
+
+    let (surface, ctx) = create_png_surface(page_width.ceil() as i32, page_height.ceil() as i32)?;
+    draw_page(&ctx, &fills)?;
 
     let mut file = fs::File::create(output).context("Failed to create PNG file")?;
     surface
         .write_to_png(&mut file)
         .context("Failed to write PNG")?;
 
-    render_svg(svg_output, width_f, height_f, |ctx| {
-        render_sbgnml(ctx, &transform, &glyphs, &arcs, show_clone_markers)
+    render_svg(svg_output, page_width, page_height, |ctx| {
+        draw_page(ctx, &fills)
     })?;
+    let annotated = annotated_rects(glyphs, &transform);
+    annotations::add_svg_annotations(svg_output, &annotated)?;
+    if let Some(pdf_output) = options.pdf_output.as_deref().filter(|_| top_level) {
+        render_pdf(pdf_output, page_width, page_height, |ctx| {
+            draw_page(ctx, &fills)?;
+            annotations::add_pdf_links(ctx, &annotated);
+            Ok(())
+        })?;
+    }
+    if let (Some(path), Some(overlay), Some(row_ids), true) =
+        (&options.animation, &options.overlay, &row_ids, top_level)
+    {
+        let frames: Vec<(String, overlay::Fills)> = overlay
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(column, caption)| (caption.clone(), overlay.fills(column, row_ids)))
+            .collect();
+        animation::write_animation(
+            path,
+            &frames,
+            page_width,
+            page_height,
+            options.frame_delay,
+            draw_page,
+        )?;
+    }
+    if let Some(legend) = &legend {
+        legend.write_separate(output)?;
+    }
+    Ok(transform)
+}
+
+/// Glyphs carrying notes or cross-references, with their area in the rendered image.
+fn annotated_rects<'a>(glyphs: &'a [Glyph], transform: &Transform) -> Vec<(PixelRect, &'a Glyph)> {
+    glyphs
+        .iter()
+        .filter(|glyph| annotations::has_annotations(glyph))
+        .filter_map(|glyph| Some((bbox_pixel_rect(transform, glyph.bbox?), glyph)))
+        .collect()
+}
+
+/// Write the Cytoscape JSON, GraphML, DOT and annotation exports that were requested.
+fn write_exports(
+    glyphs: &[Glyph],
+    arcs: &[Arc],
+    transform: &Transform,
+    options: &DrawOptions,
+) -> Result<()> {
+    if let Some(path) = &options.emit_cytoscape {
+        network::write_cytoscape_json(path, glyphs, arcs)?;
+    }
+    if let Some(path) = &options.emit_graphml {
+        network::write_graphml(path, glyphs, arcs)?;
+    }
+    if let Some(path) = &options.emit_dot {
+        dot::write_dot(path, glyphs, arcs)?;
+    }
+    if let Some(path) = &options.emit_annotations {
+        annotations::write_annotations_json(path, &annotated_rects(glyphs, transform))?;
+    }
+    Ok(())
+}
+
+/// Render one `<map>` to PNG and SVG, then any submaps it links to when requested.
+fn draw_map<'a, 'input>(
+    doc: &'a Document<'input>,
+    map_node: roxmltree::Node<'a, 'input>,
+    output: &Path,
+    svg_output: &Path,
+    options: &DrawOptions,
+    rendered: &mut HashSet<roxmltree::NodeId>,
+) -> Result<()> {
+    let is_top_map = rendered.is_empty();
+    rendered.insert(map_node.id());
+    let (mut glyphs, mut arcs) = parse_map(&map_node)?;
+    let transform = render_outputs(
+        &mut glyphs,
+        &mut arcs,
+        output,
+        svg_output,
+        is_top_map,
+        options,
+    )?;
+    // The map named by --input is exported with its layout; submaps are copied as they were.
+    if let (Some(path), true) = (&options.emit_sbgn, is_top_map) {
+        let info = export::MapInfo::from_map(&map_node);
+        export::write_sbgnml(path, &info, &glyphs, &arcs)?;
+    }
+    if is_top_map {
+        write_exports(&glyphs, &arcs, &transform, options)?;
+    }
+
+    if !options.submap_outputs {
+        return Ok(());
+    }
+    let mut links = Vec::new();
+    let mut pending = Vec::new();
+    for glyph in glyphs.iter().filter(|glyph| glyph.class_name == "submap") {
+        let (Some(bbox), Some(submap_node)) = (glyph.bbox, find_submap_node(doc, glyph)) else {
+            continue;
+        };
+        let submap_output = submap_output_path(output, &submap_node, glyph);
+        let submap_svg = default_svg_output_path(&submap_output);
+        let href = submap_svg
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        links.push((bbox_pixel_rect(&transform, bbox), href));
+        pending.push((submap_node, submap_output, submap_svg));
+    }
+    add_svg_links(svg_output, &links)?;
+    for (submap_node, submap_output, submap_svg) in pending {
+        if rendered.contains(&submap_node.id()) {
+            continue;
+        }
+        draw_map(
+            doc,
+            submap_node,
+            &submap_output,
+            &submap_svg,
+            options,
+            rendered,
+        )?;
+    }
     Ok(())
 }
 
+/// Find the `<map>` holding a submap's content, matched by map id against the glyph id or label.
+fn find_submap_node<'a, 'input>(
+    doc: &'a Document<'input>,
+    glyph: &Glyph,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    let label = glyph.label.trim();
+    doc.descendants()
+        .filter(|node| node.has_tag_name("map"))
+        .find(|node| {
+            node.attribute("id")
+                .is_some_and(|id| id == glyph.id || (!label.is_empty() && id == label))
+        })
+}
+
+/// Name a submap's output after the parent output plus the submap's map id.
+fn submap_output_path(output: &Path, map_node: &roxmltree::Node, glyph: &Glyph) -> PathBuf {
+    let name: String = map_node
+        .attribute("id")
+        .unwrap_or(glyph.id.as_str())
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = output
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "png".to_string());
+    output.with_file_name(format!("{stem}_{name}.{extension}"))
+}
+
+/// Overlay transparent hyperlink areas on a finished SVG, one per linked rect.
+fn add_svg_links(svg_path: &Path, links: &[(PixelRect, String)]) -> Result<()> {
+    if links.is_empty() {
+        return Ok(());
+    }
+    let mut anchors = String::new();
+    for (rect, href) in links {
+        let href = xml_escape(href);
+        anchors.push_str(&format!(
+            "<a xlink:href=\"{href}\" href=\"{href}\"><rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"#000\" fill-opacity=\"0\" style=\"cursor:pointer\"/></a>\n",
+            rect.x0, rect.y0, rect.width, rect.height
+        ));
+    }
+    insert_svg_overlay(svg_path, &anchors)
+}
+
+/// Append markup to a written SVG, on top of everything Cairo drew.
+fn insert_svg_overlay(svg_path: &Path, overlay: &str) -> Result<()> {
+    let svg = fs::read_to_string(svg_path)
+        .with_context(|| format!("Failed to read {:?} for linking", svg_path))?;
+    let close = svg
+        .rfind("</svg>")
+        .ok_or_else(|| anyhow!("SVG output {:?} is missing </svg>", svg_path))?;
+    let mut linked = svg;
+    linked.insert_str(close, overlay);
+    fs::write(svg_path, linked).with_context(|| format!("Failed to write {:?}", svg_path))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 /// Render parsed SBGNML glyphs and arcs using bbox geometry.
 fn render_sbgnml(
     ctx: &CairoContext,
@@ -243,6 +927,8 @@ fn render_sbgnml(
     glyphs: &[Glyph],
     arcs: &[Arc],
     show_clone_markers: bool,
+    highlight: &Highlight,
+    fills: &overlay::Fills,
 ) -> Result<()> {
     let mut child_map: HashMap<String, Vec<&Glyph>> = HashMap::new();
     for glyph in glyphs {
@@ -253,17 +939,73 @@ fn render_sbgnml(
 
     let aux_glyphs: Vec<&Glyph> = glyphs
         .iter()
-        .filter(|glyph| {
-            glyph.parent_id.is_some()
-                && matches!(
-                    glyph.class_name.as_str(),
-                    "unit of information" | "state variable"
-                )
+        .filter(|glyph| glyph.parent_id.is_some() && is_aux_class(&glyph.class_name))
+        .collect();
+
+    // Paint in layers so stacking does not depend on XML order: compartments (outermost
+    // first), then arcs, then complexes, then all other nodes.
+    let by_id: HashMap<&str, &Glyph> = glyphs
+        .iter()
+        .map(|glyph| (glyph.id.as_str(), glyph))
+        .collect();
+    let roots: Vec<&Glyph> = glyphs
+        .iter()
+        .filter(|glyph| match glyph.parent_id.as_deref() {
+            None => true,
+            Some(parent_id) => {
+                !is_aux_class(&glyph.class_name)
+                    && by_id
+                        .get(parent_id)
+                        .is_some_and(|parent| parent.class_name == "compartment")
+            }
         })
         .collect();
+    let mut compartments: Vec<&Glyph> = roots
+        .iter()
+        .copied()
+        .filter(|glyph| glyph.class_name == "compartment")
+        .collect();
+    // compartmentOrder wins when given (higher is painted later); nesting and size
+    // break ties, so files without it still paint outer compartments first.
+    compartments.sort_by(|a, b| {
+        let order = |glyph: &Glyph| glyph.compartment_order.unwrap_or(0.0);
+        order(a)
+            .total_cmp(&order(b))
+            .then(compartment_depth(a, &by_id).cmp(&compartment_depth(b, &by_id)))
+            .then(bbox_area(b).total_cmp(&bbox_area(a)))
+    });
+    let mut complexes: Vec<&Glyph> = roots
+        .iter()
+        .copied()
+        .filter(|glyph| glyph.class_name.starts_with("complex"))
+        .collect();
+    complexes.sort_by(|a, b| bbox_area(b).total_cmp(&bbox_area(a)));
+    let entities = roots.iter().copied().filter(|glyph| {
+        glyph.class_name != "compartment" && !glyph.class_name.starts_with("complex")
+    });
 
-    for glyph in glyphs.iter().filter(|glyph| glyph.parent_id.is_none()) {
-        render_glyph_tree(ctx, transform, glyph, &child_map, show_clone_markers)?;
+    let render_root = |glyph: &Glyph| {
+        let parent_lit = glyph
+            .parent_id
+            .as_deref()
+            .is_some_and(|parent_id| highlight.contains(parent_id));
+        render_glyph_tree(
+            ctx,
+            transform,
+            glyph,
+            &child_map,
+            show_clone_markers,
+            highlight,
+            fills,
+            parent_lit,
+        )
+    };
+    for glyph in compartments {
+        render_root(glyph)?;
+    }
+    draw_arcs(ctx, transform, arcs, highlight)?;
+    for glyph in complexes.into_iter().chain(entities) {
+        render_root(glyph)?;
     }
 
     // Render auxiliary glyphs at their absolute bbox positions.
@@ -273,27 +1015,94 @@ fn render_sbgnml(
             None => continue,
         };
         let class_name = glyph.class_name.as_str();
-        let label = if class_name == "state variable" && glyph.label.trim().is_empty() {
-            state_var_label(
-                glyph.state_value.as_deref(),
-                glyph.state_variable.as_deref(),
-            )
-        } else {
-            glyph.label.clone()
-        };
+        let label = aux_label(glyph);
         let font_px = glyph_font_px(class_name);
         let has_clone = show_clone_markers && glyph.has_clone;
-        match class_name {
-            "unit of information" => {
-                draw_round_rect_bbox(ctx, transform, bbox, &label, font_px, has_clone)?
+        let emphasized = highlight.contains(&glyph.id);
+        let lit = emphasized
+            || glyph
+                .parent_id
+                .as_deref()
+                .is_some_and(|parent_id| highlight.contains(parent_id));
+        with_opacity(ctx, highlight.dim_alpha(lit), || {
+            let rect = bbox_pixel_rect(transform, bbox);
+            if emphasized {
+                draw_highlight_glow(
+                    ctx,
+                    rect,
+                    class_name,
+                    glyph.orientation.as_deref(),
+                    highlight.color,
+                )?;
             }
-            "state variable" => {
-                draw_stadium_bbox(ctx, transform, bbox, &label, font_px, has_clone)?
+            match class_name {
+                "unit of information" => draw_unit_info_bbox(
+                    ctx,
+                    transform,
+                    bbox,
+                    &label,
+                    font_px,
+                    has_clone,
+                    glyph.entity.as_deref(),
+                )?,
+                "existence" | "location" => {
+                    draw_existence_marker(ctx, rect, class_name, DEFAULT_LINE_WIDTH)?
+                }
+                "state variable" => {
+                    draw_stadium_bbox(ctx, transform, bbox, &label, font_px, has_clone)?
+                }
+                _ => {}
+            }
+            if emphasized {
+                draw_highlight_outline(
+                    ctx,
+                    rect,
+                    class_name,
+                    glyph.orientation.as_deref(),
+                    highlight.color,
+                )?;
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Number of compartments enclosing `glyph`, via XML nesting or `compartmentRef`.
+fn compartment_depth(glyph: &Glyph, by_id: &HashMap<&str, &Glyph>) -> usize {
+    let mut depth = 0;
+    let mut current = glyph;
+    // Bounded by the glyph count so cyclic compartmentRefs cannot loop forever.
+    while depth < by_id.len() {
+        let outer = current
+            .compartment_ref
+            .as_deref()
+            .or(current.parent_id.as_deref())
+            .and_then(|id| by_id.get(id))
+            .filter(|outer| outer.class_name == "compartment");
+        match outer {
+            Some(outer) => {
+                depth += 1;
+                current = outer;
             }
-            _ => {}
+            None => break,
         }
     }
+    depth
+}
+
+fn bbox_area(glyph: &Glyph) -> f64 {
+    glyph.bbox.map(|bbox| bbox.w * bbox.h).unwrap_or(0.0)
+}
 
+/// Draw all arcs with their stoichiometry and cardinality labels.
+fn draw_arcs(
+    ctx: &CairoContext,
+    transform: &Transform,
+    arcs: &[Arc],
+    highlight: &Highlight,
+) -> Result<()> {
     let arrow_size_px = transform.scale_scalar(ARROW_SIZE * ARROW_SCALE);
     let bar_length_px = transform.scale_scalar(BAR_LENGTH * ARROW_SCALE);
     let bar_offset_px = transform.scale_scalar(BAR_OFFSET * ARROW_SCALE);
@@ -304,24 +1113,197 @@ fn render_sbgnml(
             .iter()
             .map(|pt| transform.map_point(pt.x, pt.y))
             .collect();
-        draw_arc(
-            ctx,
-            &points_px,
-            &arc.class_name,
-            arrow_size_px,
-            bar_length_px,
-            bar_offset_px,
-        )?;
+        let emphasized = highlight.contains(&arc.id);
+        with_opacity(ctx, highlight.dim_alpha(emphasized), || {
+            if emphasized {
+                draw_highlight_polyline_glow(ctx, &points_px, highlight.color)?;
+            }
+            let (color, line_width) = if emphasized {
+                (highlight.color, HIGHLIGHT_LINE_WIDTH)
+            } else {
+                (theme::palette().border, DEFAULT_LINE_WIDTH)
+            };
+            draw_arc(
+                ctx,
+                &points_px,
+                &arc.class_name,
+                arrow_size_px,
+                bar_length_px,
+                bar_offset_px,
+                color,
+                line_width,
+            )?;
+            draw_arc_glyphs(ctx, transform, arc, &points_px)
+        })?;
+    }
+    Ok(())
+}
+
+/// Draw stoichiometry and cardinality boxes carried by an arc.
+fn draw_arc_glyphs(
+    ctx: &CairoContext,
+    transform: &Transform,
+    arc: &Arc,
+    points_px: &[Point],
+) -> Result<()> {
+    for glyph in arc.glyphs.iter().filter(|glyph| is_arc_label(glyph)) {
+        let font_px = glyph_font_px(&glyph.class_name);
+        let rect = match glyph.bbox {
+            Some(bbox) => bbox_pixel_rect(transform, bbox),
+            None => {
+                let Some(center) = arc_label_anchor(points_px, &arc.class_name) else {
+                    continue;
+                };
+                let (width, height) = arc_label_size(ctx, glyph);
+                PixelRect {
+                    x0: center.x - width / 2.0,
+                    y0: center.y - height / 2.0,
+                    width,
+                    height,
+                    center,
+                }
+            }
+        };
+        path_rect(ctx, rect)?;
+        ctx.set_line_width(DEFAULT_LINE_WIDTH);
+        set_color(ctx, theme::palette().paper);
+        ctx.fill_preserve()?;
+        set_color(ctx, theme::palette().border);
+        ctx.stroke()?;
+        draw_text_centered(ctx, rect.center, &glyph.label, font_px)?;
+    }
+    Ok(())
+}
+
+/// Stoichiometry and cardinality glyphs with text, drawn as boxes on their arc.
+fn is_arc_label(glyph: &Glyph) -> bool {
+    matches!(glyph.class_name.as_str(), "stoichiometry" | "cardinality")
+        && !glyph.label.trim().is_empty()
+}
+
+/// Pixel size of the box drawn for an arc label that has no stored bbox.
+fn arc_label_size(ctx: &CairoContext, glyph: &Glyph) -> (f64, f64) {
+    let font_px = glyph_font_px(&glyph.class_name);
+    let width = (measure_text_width(ctx, &glyph.label, font_px) + 8.0).max(16.0);
+    (width, font_px + 6.0)
+}
+
+/// Point on an arc, a fixed distance from its entity pool end, where a stoichiometry box goes.
+fn arc_label_anchor(points: &[Point], class_name: &str) -> Option<Point> {
+    if points.len() < 2 {
+        return None;
+    }
+    // Production arcs end at the entity pool; the other arcs start from it.
+    let path: Vec<Point> = if class_name == "production" {
+        points.iter().rev().copied().collect()
+    } else {
+        points.to_vec()
+    };
+    let mut remaining = ARC_LABEL_OFFSET_PX;
+    for pair in path.windows(2) {
+        let dx = pair[1].x - pair[0].x;
+        let dy = pair[1].y - pair[0].y;
+        let length = dx.hypot(dy);
+        if length >= remaining && length > 0.0 {
+            return Some(Point {
+                x: pair[0].x + dx / length * remaining,
+                y: pair[0].y + dy / length * remaining,
+            });
+        }
+        remaining -= length;
+    }
+    let first = path[0];
+    let last = path[path.len() - 1];
+    Some(Point {
+        x: (first.x + last.x) / 2.0,
+        y: (first.y + last.y) / 2.0,
+    })
+}
+
+/// Run a drawing closure, compositing its output at the given opacity when one is set.
+fn with_opacity<F>(ctx: &CairoContext, alpha: Option<f64>, draw: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let Some(alpha) = alpha else {
+        return draw();
+    };
+    ctx.push_group();
+    let result = draw();
+    ctx.pop_group_to_source()?;
+    ctx.paint_with_alpha(alpha)?;
+    set_color(ctx, theme::palette().border);
+    result
+}
+
+/// Paint a soft halo around a glyph outline, drawn before the glyph so its fill covers the inside.
+fn draw_highlight_glow(
+    ctx: &CairoContext,
+    rect: PixelRect,
+    class_name: &str,
+    orientation: Option<&str>,
+    color: (f64, f64, f64),
+) -> Result<()> {
+    path_glyph_outline(ctx, rect, class_name, orientation)?;
+    ctx.set_source_rgba(color.0, color.1, color.2, HIGHLIGHT_GLOW_ALPHA);
+    ctx.set_line_width(HIGHLIGHT_GLOW_WIDTH);
+    ctx.stroke()?;
+    set_color(ctx, theme::palette().border);
+    ctx.set_line_width(DEFAULT_LINE_WIDTH);
+    Ok(())
+}
+
+/// Stroke a glyph outline with the thicker highlight border.
+fn draw_highlight_outline(
+    ctx: &CairoContext,
+    rect: PixelRect,
+    class_name: &str,
+    orientation: Option<&str>,
+    color: (f64, f64, f64),
+) -> Result<()> {
+    path_glyph_outline(ctx, rect, class_name, orientation)?;
+    ctx.set_source_rgb(color.0, color.1, color.2);
+    ctx.set_line_width(HIGHLIGHT_LINE_WIDTH);
+    ctx.stroke()?;
+    set_color(ctx, theme::palette().border);
+    ctx.set_line_width(DEFAULT_LINE_WIDTH);
+    Ok(())
+}
+
+/// Paint a soft halo along an arc path.
+fn draw_highlight_polyline_glow(
+    ctx: &CairoContext,
+    points: &[Point],
+    color: (f64, f64, f64),
+) -> Result<()> {
+    let Some((first, rest)) = points.split_first() else {
+        return Ok(());
+    };
+    ctx.new_path();
+    ctx.move_to(first.x, first.y);
+    for point in rest {
+        ctx.line_to(point.x, point.y);
     }
+    ctx.set_source_rgba(color.0, color.1, color.2, HIGHLIGHT_GLOW_ALPHA);
+    ctx.set_line_width(HIGHLIGHT_GLOW_WIDTH);
+    ctx.set_line_join(LineJoin::Round);
+    ctx.stroke()?;
+    ctx.set_line_join(LineJoin::Miter);
+    set_color(ctx, theme::palette().border);
+    ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_glyph_tree(
     ctx: &CairoContext,
     transform: &Transform,
     glyph: &Glyph,
     child_map: &HashMap<String, Vec<&Glyph>>,
     show_clone_markers: bool,
+    highlight: &Highlight,
+    fills: &overlay::Fills,
+    parent_lit: bool,
 ) -> Result<()> {
     let bbox = match glyph.bbox {
         Some(bbox) => bbox,
@@ -335,39 +1317,43 @@ fn render_glyph_tree(
         "and" => Some("AND"),
         "or" => Some("OR"),
         "not" => Some("NOT"),
+        "equivalence" => Some("≡"),
         "omitted process" => Some("\\\\"),
         "uncertain process" => Some("?"),
         _ => None,
     };
-    let mut label = label_override.unwrap_or(glyph.label.as_str()).to_string();
-    if class_name == "state variable" && label.trim().is_empty() {
-        label = state_var_label(
-            glyph.state_value.as_deref(),
-            glyph.state_variable.as_deref(),
-        );
-    }
+    let label = match label_override {
+        Some(text) => text.to_string(),
+        None => aux_label(glyph),
+    };
     let font_px = glyph_font_px(class_name);
     let has_clone = show_clone_markers && glyph.has_clone;
     let children = child_map
         .get(&glyph.id)
         .map(|items| items.as_slice())
         .unwrap_or(&[]);
-    let has_u_info_bbox = children
+    // Aux units with a bbox are drawn where stored; the rest are placed on the node.
+    let u_info: Vec<&Glyph> = children
         .iter()
-        .any(|child| child.class_name == "unit of information" && child.bbox.is_some());
-    let has_s_var_bbox = children
+        .copied()
+        .filter(|child| {
+            child.class_name == "unit of information"
+                && child.bbox.is_none()
+                && !child.label.trim().is_empty()
+        })
+        .collect();
+    let s_vars: Vec<&Glyph> = children
         .iter()
-        .any(|child| child.class_name == "state variable" && child.bbox.is_some());
-    let u_info_label = if has_u_info_bbox {
-        None
-    } else {
-        first_child_label(children, "unit of information")
-    };
-    let s_var_label = if has_s_var_bbox {
-        None
-    } else {
-        first_child_state_label(children, "state variable")
-    };
+        .copied()
+        .filter(|child| {
+            child.bbox.is_none()
+                && match child.class_name.as_str() {
+                    "state variable" => !aux_label(child).trim().is_empty(),
+                    "existence" | "location" => true,
+                    _ => false,
+                }
+        })
+        .collect();
     let place_label_bottom = class_base == "complex" || class_name == "compartment";
     let shape_label = if place_label_bottom {
         ""
@@ -375,153 +1361,235 @@ fn render_glyph_tree(
         label.as_str()
     };
 
-    match class_name {
-        "phenotype" | "outcome" => {
-            draw_hexagon_bbox(ctx, transform, bbox, shape_label, font_px, false)?
-        }
-        "perturbing agent" => {
-            draw_entity_pool_node(
-                ctx,
-                transform,
-                bbox,
-                shape_label,
-                font_px,
-                class_base,
-                is_multimer,
-                has_clone,
-                u_info_label.as_deref(),
-                None,
-            )?;
-        }
-        "simple chemical" | "simple chemical multimer" => {
-            draw_entity_pool_node(
+    let fill = fills.get(&glyph.id).copied().or(glyph.fill);
+    let emphasized = highlight.contains(&glyph.id);
+    let lit = parent_lit || emphasized;
+    with_opacity(ctx, highlight.dim_alpha(lit), || {
+        if emphasized {
+            let rect = bbox_pixel_rect(transform, bbox);
+            draw_highlight_glow(
                 ctx,
-                transform,
-                bbox,
-                shape_label,
-                font_px,
-                class_base,
-                is_multimer,
-                has_clone,
-                u_info_label.as_deref(),
-                None,
+                rect,
+                class_name,
+                glyph.orientation.as_deref(),
+                highlight.color,
             )?;
         }
-        "unspecified entity" => {
-            draw_entity_pool_node(
+        match class_name {
+            "phenotype" | "outcome" => {
+                draw_hexagon_bbox(ctx, transform, bbox, shape_label, font_px, false)?
+            }
+            "perturbing agent" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "simple chemical" | "simple chemical multimer" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "unspecified entity" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "macromolecule" | "macromolecule multimer" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "nucleic acid feature" | "nucleic acid feature multimer" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "complex" | "complex multimer" => {
+                draw_entity_pool_node(
+                    ctx,
+                    transform,
+                    bbox,
+                    shape_label,
+                    font_px,
+                    class_base,
+                    is_multimer,
+                    has_clone,
+                    fill,
+                    &u_info,
+                    &s_vars,
+                )?;
+            }
+            "source and sink" => draw_source_sink_bbox(ctx, transform, bbox, has_clone)?,
+            "compartment" => {
+                draw_barrel_bbox(ctx, transform, bbox, shape_label, font_px, has_clone)?
+            }
+            "tag" | "terminal" => draw_tag_bbox(
                 ctx,
                 transform,
                 bbox,
                 shape_label,
                 font_px,
-                class_base,
-                is_multimer,
                 has_clone,
-                u_info_label.as_deref(),
-                s_var_label.as_deref(),
-            )?;
-        }
-        "macromolecule" | "macromolecule multimer" => {
-            draw_entity_pool_node(
+                glyph.orientation.as_deref(),
+            )?,
+            "submap" => draw_submap_bbox(ctx, transform, bbox, shape_label, font_px, has_clone)?,
+            "association" => draw_ellipse_bbox_filled(
                 ctx,
                 transform,
                 bbox,
                 shape_label,
                 font_px,
-                class_base,
-                is_multimer,
-                has_clone,
-                u_info_label.as_deref(),
-                s_var_label.as_deref(),
-            )?;
-        }
-        "nucleic acid feature" | "nucleic acid feature multimer" => {
-            draw_entity_pool_node(
+                theme::palette().association_fill,
+            )?,
+            "dissociation" => draw_double_circle_bbox(ctx, transform, bbox, shape_label, font_px)?,
+            "process" | "omitted process" | "uncertain process" => {
+                draw_square_bbox(ctx, transform, bbox, shape_label, font_px, false)?;
+                if SHOW_PROCESS_DEBUG {
+                    draw_process_debug_bbox(ctx, transform, bbox)?;
+                }
+            }
+            "unit of information" => draw_unit_info_bbox(
                 ctx,
                 transform,
                 bbox,
                 shape_label,
                 font_px,
-                class_base,
-                is_multimer,
-                has_clone,
-                u_info_label.as_deref(),
-                s_var_label.as_deref(),
-            )?;
-        }
-        "complex" | "complex multimer" => {
-            draw_entity_pool_node(
+                false,
+                glyph.entity.as_deref(),
+            )?,
+            "existence" | "location" => draw_existence_marker(
                 ctx,
-                transform,
-                bbox,
-                shape_label,
-                font_px,
-                class_base,
-                is_multimer,
-                has_clone,
-                u_info_label.as_deref(),
-                s_var_label.as_deref(),
-            )?;
-        }
-        "source and sink" => draw_source_sink_bbox(ctx, transform, bbox, has_clone)?,
-        "compartment" => draw_barrel_bbox(ctx, transform, bbox, shape_label, font_px, has_clone)?,
-        "tag" => draw_tag_bbox(ctx, transform, bbox, shape_label, font_px, has_clone)?,
-        "association" => draw_ellipse_bbox_filled(
-            ctx,
-            transform,
-            bbox,
-            shape_label,
-            font_px,
-            ASSOCIATION_FILL_COLOR,
-        )?,
-        "dissociation" => draw_double_circle_bbox(ctx, transform, bbox, shape_label, font_px)?,
-        "process" | "omitted process" | "uncertain process" => {
-            draw_square_bbox(ctx, transform, bbox, shape_label, font_px, false)?;
-            if SHOW_PROCESS_DEBUG {
-                draw_process_debug_bbox(ctx, transform, bbox)?;
+                bbox_pixel_rect(transform, bbox),
+                class_name,
+                DEFAULT_LINE_WIDTH,
+            )?,
+            "state variable" => {
+                draw_stadium_bbox(ctx, transform, bbox, shape_label, font_px, false)?
             }
-        }
-        "unit of information" => {
-            draw_round_rect_bbox(ctx, transform, bbox, shape_label, font_px, false)?
-        }
-        "state variable" => draw_stadium_bbox(ctx, transform, bbox, shape_label, font_px, false)?,
-        "and" | "or" | "not" => {
-            draw_circle_bbox(ctx, transform, bbox, shape_label, font_px)?;
-            if SHOW_LOGICAL_DEBUG_BBOX {
-                draw_logical_debug_bbox(ctx, transform, bbox)?;
+            "and" | "or" | "not" | "equivalence" => {
+                draw_circle_bbox(ctx, transform, bbox, shape_label, font_px)?;
+                if SHOW_LOGICAL_DEBUG_BBOX {
+                    draw_logical_debug_bbox(ctx, transform, bbox)?;
+                }
             }
+            _ => draw_box_bbox(ctx, transform, bbox, shape_label, font_px, false)?,
         }
-        _ => draw_box_bbox(ctx, transform, bbox, shape_label, font_px, false)?,
-    }
 
-    let orientation = glyph.orientation.as_deref().or_else(|| {
-        if matches!(
-            class_name,
-            "process" | "omitted process" | "uncertain process" | "association" | "dissociation"
-        ) {
-            Some("horizontal")
-        } else {
+        // Tags and terminals use their orientation for the shape, not for port connectors.
+        let orientation = if matches!(class_name, "tag" | "terminal") {
             None
+        } else {
+            glyph.orientation.as_deref()
+        };
+        let orientation = orientation.or(
+            if matches!(
+                class_name,
+                "process"
+                    | "omitted process"
+                    | "uncertain process"
+                    | "association"
+                    | "dissociation"
+            ) {
+                Some("horizontal")
+            } else {
+                None
+            },
+        );
+        if let Some(orientation) = orientation {
+            let connector_len_px = port_connector_len_px_for_class(class_name);
+            draw_orientation_marker(ctx, transform, bbox, orientation, connector_len_px)?;
         }
-    });
-    if let Some(orientation) = orientation {
-        let connector_len_px = port_connector_len_px_for_class(class_name);
-        draw_orientation_marker(ctx, transform, bbox, orientation, connector_len_px)?;
-    }
 
-    if place_label_bottom {
-        let rect = bbox_pixel_rect(transform, bbox);
-        draw_text_bottom_centered(ctx, rect, &label, font_px)?;
-    }
+        if let Some(clone_label) = glyph.clone_label.as_deref().filter(|_| has_clone) {
+            draw_clone_label(ctx, bbox_pixel_rect(transform, bbox), clone_label)?;
+        }
 
+        if place_label_bottom {
+            let rect = bbox_pixel_rect(transform, bbox);
+            draw_text_bottom_centered(ctx, rect, &label, font_px)?;
+        }
+
+        if emphasized {
+            let rect = bbox_pixel_rect(transform, bbox);
+            draw_highlight_outline(
+                ctx,
+                rect,
+                class_name,
+                glyph.orientation.as_deref(),
+                highlight.color,
+            )?;
+        }
+        Ok(())
+    })?;
+
+    // Compartment members are painted as roots by render_sbgnml, above the arcs.
+    if class_name == "compartment" {
+        return Ok(());
+    }
     for child in children.iter().copied() {
-        if matches!(
-            child.class_name.as_str(),
-            "unit of information" | "state variable"
-        ) {
+        if is_aux_class(&child.class_name) {
             continue;
         }
-        render_glyph_tree(ctx, transform, child, child_map, show_clone_markers)?;
+        render_glyph_tree(
+            ctx,
+            transform,
+            child,
+            child_map,
+            show_clone_markers,
+            highlight,
+            fills,
+            lit,
+        )?;
     }
 
     Ok(())
@@ -543,7 +1611,7 @@ fn draw_box_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().fill),
         path_rect,
     )
 }
@@ -561,7 +1629,7 @@ fn draw_process_debug_bbox(ctx: &CairoContext, transform: &Transform, bbox: BBox
     ctx.set_line_width(1.0);
     path_rect(ctx, inset_rect)?;
     ctx.stroke()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
@@ -572,7 +1640,7 @@ fn draw_logical_debug_bbox(ctx: &CairoContext, transform: &Transform, bbox: BBox
     ctx.set_line_width(1.0);
     path_rect(ctx, rect)?;
     ctx.stroke()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
@@ -606,7 +1674,7 @@ fn draw_square_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().fill),
         path_rect,
     )
 }
@@ -625,7 +1693,7 @@ fn draw_ellipse_bbox_filled(
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     ctx.set_source_rgb(fill.0, fill.1, fill.2);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     draw_text_centered(ctx, rect.center, label, font_px)?;
     Ok(())
@@ -649,13 +1717,9 @@ fn draw_double_circle_bbox(
         0.0,
         std::f64::consts::TAU,
     );
-    ctx.set_source_rgb(
-        DEFAULT_FILL_COLOR.0,
-        DEFAULT_FILL_COLOR.1,
-        DEFAULT_FILL_COLOR.2,
-    );
+    set_color(ctx, theme::palette().fill);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     ctx.new_path();
     ctx.arc(
@@ -665,19 +1729,20 @@ fn draw_double_circle_bbox(
         0.0,
         std::f64::consts::TAU,
     );
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     draw_text_centered(ctx, rect.center, label, font_px)?;
     Ok(())
 }
 
-fn draw_round_rect_bbox(
+fn draw_unit_info_bbox(
     ctx: &CairoContext,
     transform: &Transform,
     bbox: BBox,
     label: &str,
     font_px: f64,
     has_clone: bool,
+    entity: Option<&str>,
 ) -> Result<()> {
     let rect = bbox_pixel_rect(transform, bbox);
     draw_shape_with_clone(
@@ -687,10 +1752,10 @@ fn draw_round_rect_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().fill),
         |ctx, rect| {
             let radius = (rect.width.min(rect.height) * 0.1).max(1.0);
-            path_round_rect(ctx, rect, radius)
+            path_unit_info(ctx, rect, entity, radius)
         },
     )
 }
@@ -711,7 +1776,7 @@ fn draw_hexagon_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().fill),
         path_hexagon,
     )
 }
@@ -725,18 +1790,14 @@ fn draw_source_sink_bbox(
     let rect = bbox_pixel_rect(transform, bbox);
     path_ellipse(ctx, rect)?;
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
-    ctx.set_source_rgb(
-        DEFAULT_FILL_COLOR.0,
-        DEFAULT_FILL_COLOR.1,
-        DEFAULT_FILL_COLOR.2,
-    );
+    set_color(ctx, theme::palette().fill);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     if has_clone {
         draw_clone_marker(ctx, rect, &path_ellipse)?;
         path_ellipse(ctx, rect)?;
-        ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+        set_color(ctx, theme::palette().border);
         ctx.stroke()?;
     }
     ctx.new_path();
@@ -763,7 +1824,7 @@ fn draw_barrel_bbox(
         font_px,
         has_clone,
         border_width,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().compartment_fill),
         path_barrel,
     )
 }
@@ -775,6 +1836,7 @@ fn draw_tag_bbox(
     label: &str,
     font_px: f64,
     has_clone: bool,
+    orientation: Option<&str>,
 ) -> Result<()> {
     let rect = bbox_pixel_rect(transform, bbox);
     draw_shape_with_clone(
@@ -784,11 +1846,30 @@ fn draw_tag_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
-        |ctx, rect| {
-            let notch = (rect.height * 0.3).max(2.0);
-            path_tag(ctx, rect, notch)
-        },
+        Some(theme::palette().fill),
+        |ctx, rect| path_tag(ctx, rect, orientation),
+    )
+}
+
+/// Draw a submap as a heavier-bordered rectangle; its terminals are drawn as child glyphs.
+fn draw_submap_bbox(
+    ctx: &CairoContext,
+    transform: &Transform,
+    bbox: BBox,
+    label: &str,
+    font_px: f64,
+    has_clone: bool,
+) -> Result<()> {
+    let rect = bbox_pixel_rect(transform, bbox);
+    draw_shape_with_clone(
+        ctx,
+        rect,
+        label,
+        font_px,
+        has_clone,
+        SUBMAP_BORDER_WIDTH,
+        Some(theme::palette().fill),
+        path_rect,
     )
 }
 
@@ -808,7 +1889,7 @@ fn draw_stadium_bbox(
         font_px,
         has_clone,
         DEFAULT_LINE_WIDTH,
-        Some(DEFAULT_FILL_COLOR),
+        Some(theme::palette().fill),
         |ctx, rect| {
             let radius = 0.24 * rect.width.max(rect.height);
             path_round_rect_impl(ctx, rect.x0, rect.y0, rect.width, rect.height, radius)
@@ -817,6 +1898,7 @@ fn draw_stadium_bbox(
 }
 
 /// Draw an entity pool node using shapes and auxiliary items from sbgnStyle.
+#[allow(clippy::too_many_arguments)]
 fn draw_entity_pool_node(
     ctx: &CairoContext,
     transform: &Transform,
@@ -826,9 +1908,11 @@ fn draw_entity_pool_node(
     class_name: &str,
     is_multimer: bool,
     has_clone: bool,
-    u_info_label: Option<&str>,
-    s_var_label: Option<&str>,
+    fill: Option<(f64, f64, f64)>,
+    u_info: &[&Glyph],
+    s_vars: &[&Glyph],
 ) -> Result<()> {
+    let fill = Some(fill.unwrap_or_else(|| entity_pool_fill_color(class_name)));
     let rect = bbox_pixel_rect(transform, bbox);
     let (ref_w, ref_h) = default_dimensions(class_name).unwrap_or((rect.width, rect.height));
     let scale_x = rect.width / ref_w;
@@ -853,7 +1937,7 @@ fn draw_entity_pool_node(
                 "",
                 FONT_SMALL_PX,
                 false,
-                entity_pool_fill_color(class_name),
+                fill,
                 entity_pool_border_width(class_name),
             )?;
         }
@@ -866,15 +1950,16 @@ fn draw_entity_pool_node(
         label,
         font_px,
         has_clone,
-        entity_pool_fill_color(class_name),
+        fill,
         entity_pool_border_width(class_name),
     )?;
 
-    draw_entity_pool_aux_items(ctx, rect, class_name, u_info_label, s_var_label)?;
+    draw_entity_pool_aux_items(ctx, rect, class_name, label, font_px, u_info, s_vars)?;
     Ok(())
 }
 
 /// Draw the base shape for entity pool nodes without labels or overlays.
+#[allow(clippy::too_many_arguments)]
 fn draw_entity_pool_base_shape(
     ctx: &CairoContext,
     rect: PixelRect,
@@ -958,11 +2043,10 @@ fn draw_entity_pool_base_shape(
     }
 }
 
-/// Map entity pool nodes to their fill colors, matching sbgnStyle defaults.
-fn entity_pool_fill_color(class_name: &str) -> Option<(f64, f64, f64)> {
+fn entity_pool_fill_color(class_name: &str) -> (f64, f64, f64) {
     match class_name {
-        "complex" => Some(DEFAULT_FILL_COLOR),
-        _ => Some(DEFAULT_FILL_COLOR),
+        "complex" => theme::palette().complex_fill,
+        _ => theme::palette().fill,
     }
 }
 
@@ -989,12 +2073,18 @@ fn draw_entity_pool_aux_items(
     ctx: &CairoContext,
     rect: PixelRect,
     class_name: &str,
-    u_info_label: Option<&str>,
-    s_var_label: Option<&str>,
+    label: &str,
+    label_font_px: f64,
+    u_info: &[&Glyph],
+    s_vars: &[&Glyph],
 ) -> Result<()> {
     // Auxiliary overlays (clone markers, unit info, state vars) are positioned in absolute
     // pixel space in sbgnStyle, so we scale them relative to the node's default dimensions.
-    let (ref_w, ref_h) = default_dimensions(class_name).unwrap_or((rect.width, rect.height));
+    // Complexes have no meaningful default size, so their overlays stay at unit scale.
+    let (ref_w, ref_h) = match class_name {
+        "complex" => (rect.width, rect.height),
+        _ => default_dimensions(class_name).unwrap_or((rect.width, rect.height)),
+    };
     let scale_x = rect.width / ref_w;
     let scale_y = rect.height / ref_h;
     let scale = (scale_x + scale_y) / 2.0;
@@ -1005,265 +2095,224 @@ fn draw_entity_pool_aux_items(
     let clone_shrink_y = 3.0 * scale_y;
     let u_info_height = aux_item_height - clone_shrink_y;
 
-    match class_name {
-        "simple chemical" => {
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 8.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 52.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if let Some(label) = u_info_label {
-                let u_info_x = px_x(rect, 12.0, scale_x);
-                let u_info_y = px_y(rect, 0.0, scale_y);
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
-            }
+    let has_u_info = !u_info.is_empty();
+    let has_s_var = !s_vars.is_empty();
+    let overlay_lines: &[(f64, bool)] = match class_name {
+        "simple chemical" => &[(8.0, has_u_info), (52.0, has_u_info)],
+        "unspecified entity" | "macromolecule" => {
+            &[(8.0, has_u_info || has_s_var), (52.0, has_u_info)]
         }
-        "unspecified entity" => {
-            if u_info_label.is_some() || s_var_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 8.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 52.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if let Some(label) = u_info_label {
-                let u_info_x = px_x(rect, 20.0, scale_x);
-                let u_info_y = px_y(rect, 44.0, scale_y);
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
-            }
-            if let Some(label) = s_var_label {
-                let s_var_x = px_x(rect, 40.0, scale_x);
-                let s_var_y = rect.y0;
-                draw_state_var(
-                    ctx,
-                    s_var_x,
-                    s_var_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    10.0 * scale,
-                    30.0 * scale,
-                )?;
-            }
+        "nucleic acid feature" => &[(8.0, has_s_var), (52.0, has_u_info)],
+        "perturbing agent" => &[(8.0, has_u_info), (56.0, has_u_info)],
+        _ => &[],
+    };
+    for (offset, visible) in overlay_lines {
+        if *visible {
+            draw_overlay_line(
+                ctx,
+                rect,
+                px_y(rect, *offset, scale_y),
+                1.0 * scale,
+                theme::palette().aux_line,
+            )?;
         }
-        "macromolecule" => {
-            if u_info_label.is_some() || s_var_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 8.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 52.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if let Some(label) = u_info_label {
-                let u_info_x = px_x(rect, 20.0, scale_x);
-                let u_info_y = px_y(rect, 44.0, scale_y);
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
-            }
-            if let Some(label) = s_var_label {
-                let s_var_x = px_x(rect, 40.0, scale_x);
-                let s_var_y = rect.y0;
-                draw_state_var(
-                    ctx,
-                    s_var_x,
-                    s_var_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    10.0 * scale,
-                    30.0 * scale,
-                )?;
-            }
+    }
+    let item_height = if class_name == "complex" {
+        if has_u_info || has_s_var {
+            draw_overlay_line(
+                ctx,
+                rect,
+                px_y(rect, 11.0, scale_y),
+                6.0 * scale,
+                theme::palette().border,
+            )?;
         }
-        "nucleic acid feature" => {
-            if s_var_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 8.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 52.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
-            }
-            if let Some(label) = u_info_label {
-                let u_info_x = px_x(rect, 20.0, scale_x);
-                let u_info_y = px_y(rect, 44.0, scale_y);
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
-            }
-            if let Some(label) = s_var_label {
-                let s_var_x = px_x(rect, 40.0, scale_x);
-                let s_var_y = rect.y0;
-                draw_state_var(
-                    ctx,
-                    s_var_x,
-                    s_var_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    10.0 * scale,
-                    30.0 * scale,
-                )?;
+        24.0 * scale_y - clone_shrink_y
+    } else {
+        u_info_height
+    };
+
+    let label_rect = if label.trim().is_empty() {
+        None
+    } else {
+        let (width, height) = measure_text_size(ctx, label, label_font_px);
+        Some(PixelRect {
+            x0: rect.center.x - width / 2.0,
+            y0: rect.center.y - height / 2.0,
+            width,
+            height,
+            center: rect.center,
+        })
+    };
+    // Complexes carry their label along the bottom border, so keep aux units off it.
+    let edges: &[BorderEdge] = if class_name == "complex" {
+        &[BorderEdge::Top, BorderEdge::Left, BorderEdge::Right]
+    } else {
+        &[
+            BorderEdge::Top,
+            BorderEdge::Bottom,
+            BorderEdge::Left,
+            BorderEdge::Right,
+        ]
+    };
+    let unit_edges: Vec<BorderEdge> = if class_name == "complex" {
+        edges.to_vec()
+    } else {
+        let mut order = edges.to_vec();
+        order.swap(0, 1);
+        order
+    };
+
+    let mut placed: Vec<PixelRect> = label_rect.into_iter().collect();
+    let mut items = Vec::new();
+    for (item, preferred) in s_vars
+        .iter()
+        .map(|item| (*item, edges))
+        .chain(u_info.iter().map(|item| (*item, unit_edges.as_slice())))
+    {
+        let width = aux_unit_width(ctx, item, item_height, font_px, scale);
+        let slot = place_on_border(rect, width, item_height, preferred, &placed);
+        placed.push(slot);
+        items.push((item, slot));
+    }
+
+    for (item, slot) in items {
+        match item.class_name.as_str() {
+            "existence" | "location" => {
+                draw_existence_marker(ctx, slot, &item.class_name, border_width)?
             }
+            "unit of information" => draw_unit_info(
+                ctx,
+                slot,
+                &item.label,
+                item.entity.as_deref(),
+                border_width,
+                font_px,
+            )?,
+            _ => draw_state_var(ctx, slot, &aux_label(item), border_width, font_px)?,
         }
-        "complex" => {
-            if u_info_label.is_some() || s_var_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 11.0, scale_y),
-                    6.0 * scale,
-                    BORDER_COLOR,
-                )?;
-            }
-            if let Some(label) = u_info_label {
-                let u_info_x = rect.x0 + rect.width * 0.25;
-                let u_info_y = rect.y0;
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    24.0 * scale_y - clone_shrink_y,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
-            }
-            if let Some(label) = s_var_label {
-                let s_var_x = rect.x0 + rect.width * 0.88;
-                let s_var_y = rect.y0;
-                draw_state_var(
-                    ctx,
-                    s_var_x,
-                    s_var_y,
-                    24.0 * scale_y - clone_shrink_y,
-                    label,
-                    border_width,
-                    font_px,
-                    10.0 * scale,
-                    30.0 * scale,
-                )?;
-            }
+    }
+    Ok(())
+}
+
+/// Side of a node's bounding box that auxiliary units are placed along.
+#[derive(Clone, Copy, Debug)]
+enum BorderEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Width of an auxiliary unit drawn at `height`, sized from its label.
+fn aux_unit_width(ctx: &CairoContext, item: &Glyph, height: f64, font_px: f64, scale: f64) -> f64 {
+    match item.class_name.as_str() {
+        "existence" | "location" => height,
+        "unit of information" => {
+            (measure_text_width(ctx, &item.label, font_px) + 5.0 * scale).max(10.0)
         }
-        "perturbing agent" => {
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 8.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
+        _ => (measure_text_width(ctx, &aux_label(item), font_px) + 10.0 * scale).max(30.0 * scale),
+    }
+}
+
+/// Find a spot on the node border for a `width` x `height` unit that does not overlap `placed`.
+///
+/// Edges are tried in order; along an edge, positions fan out from its midpoint. If the
+/// border is full, the unit is appended after the last unit on the first edge.
+fn place_on_border(
+    rect: PixelRect,
+    width: f64,
+    height: f64,
+    edges: &[BorderEdge],
+    placed: &[PixelRect],
+) -> PixelRect {
+    let gap = height * 0.25;
+    let slot_at = |edge: BorderEdge, along: f64| match edge {
+        BorderEdge::Top | BorderEdge::Bottom => {
+            let center_y = match edge {
+                BorderEdge::Top => rect.y0,
+                _ => rect.y0 + rect.height,
+            };
+            aux_slot(along - width / 2.0, center_y - height / 2.0, width, height)
+        }
+        // Side units sit just inside the border so wide labels do not leave the canvas.
+        BorderEdge::Left => aux_slot(rect.x0 + gap, along - height / 2.0, width, height),
+        BorderEdge::Right => aux_slot(
+            rect.x0 + rect.width - gap - width,
+            along - height / 2.0,
+            width,
+            height,
+        ),
+    };
+    let is_free = |slot: &PixelRect| {
+        placed
+            .iter()
+            .all(|other| !pixel_rects_overlap(slot, other, gap))
+    };
+
+    for edge in edges.iter().copied() {
+        // Keep clear of rounded/cut corners by a tenth of the edge length.
+        let (mid, half_span, extent) = match edge {
+            BorderEdge::Top | BorderEdge::Bottom => (rect.center.x, rect.width / 2.0, width),
+            BorderEdge::Left | BorderEdge::Right => (rect.center.y, rect.height / 2.0, height),
+        };
+        let max_offset = half_span * 0.9 - extent / 2.0;
+        if max_offset < 0.0 {
+            continue;
+        }
+        let step = 1.0_f64.max(max_offset / 64.0);
+        let mut offset = 0.0;
+        while offset <= max_offset {
+            for along in [mid + offset, mid - offset] {
+                let slot = slot_at(edge, along);
+                if is_free(&slot) {
+                    return slot;
+                }
             }
-            if u_info_label.is_some() {
-                draw_overlay_line(
-                    ctx,
-                    rect,
-                    px_y(rect, 56.0, scale_y),
-                    1.0 * scale,
-                    AUX_LINE_COLOR,
-                )?;
+            offset += step;
+        }
+    }
+
+    let edge = edges.first().copied().unwrap_or(BorderEdge::Top);
+    // Only the cross-edge coordinate of the probe matters for matching units on `edge`.
+    let probe = slot_at(edge, 0.0);
+    let last_end = placed
+        .iter()
+        .filter_map(|other| match edge {
+            BorderEdge::Top | BorderEdge::Bottom => {
+                ((other.center.y - probe.center.y).abs() < 0.5).then_some(other.x0 + other.width)
             }
-            if let Some(label) = u_info_label {
-                let u_info_x = px_x(rect, 20.0, scale_x);
-                let u_info_y = rect.y0;
-                draw_unit_info(
-                    ctx,
-                    u_info_x,
-                    u_info_y,
-                    u_info_height,
-                    label,
-                    border_width,
-                    font_px,
-                    5.0 * scale,
-                )?;
+            BorderEdge::Left | BorderEdge::Right => {
+                ((other.x0 - probe.x0).abs() < 0.5).then_some(other.y0 + other.height)
             }
-        }
-        _ => {}
+        })
+        .fold(f64::NEG_INFINITY, f64::max);
+    let along = match edge {
+        BorderEdge::Top | BorderEdge::Bottom => last_end.max(rect.x0) + gap + width / 2.0,
+        BorderEdge::Left | BorderEdge::Right => last_end.max(rect.y0) + gap + height / 2.0,
+    };
+    slot_at(edge, along)
+}
+
+fn aux_slot(x0: f64, y0: f64, width: f64, height: f64) -> PixelRect {
+    PixelRect {
+        x0,
+        y0,
+        width,
+        height,
+        center: Point {
+            x: x0 + width / 2.0,
+            y: y0 + height / 2.0,
+        },
     }
-    Ok(())
+}
+
+/// Whether two rects come within `gap` px of each other.
+fn pixel_rects_overlap(a: &PixelRect, b: &PixelRect, gap: f64) -> bool {
+    a.x0 < b.x0 + b.width + gap
+        && b.x0 < a.x0 + a.width + gap
+        && a.y0 < b.y0 + b.height + gap
+        && b.y0 < a.y0 + a.height + gap
 }
 
 /// Draw an orientation marker line for glyphs that define an orientation.
@@ -1275,7 +2324,7 @@ fn draw_orientation_marker(
     connector_len_px: f64,
 ) -> Result<()> {
     let rect = bbox_pixel_rect(transform, bbox);
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     match orientation {
         "vertical" => {
@@ -1337,90 +2386,53 @@ fn draw_overlay_line(
     ctx.move_to(rect.x0, y);
     ctx.line_to(rect.x0 + rect.width, y);
     ctx.stroke()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
 
 fn port_connector_len_px_for_class(class_name: &str) -> f64 {
-    if matches!(class_name, "and" | "or" | "not") {
+    if matches!(class_name, "and" | "or" | "not" | "equivalence") {
         LOGICAL_PORT_CONNECTOR_LEN_PX
     } else {
         PORT_CONNECTOR_LEN_PX
     }
 }
 
-/// Draw a unit of information box sized from label width.
+/// Draw a unit of information box in `rect`.
 fn draw_unit_info(
     ctx: &CairoContext,
-    x: f64,
-    y: f64,
-    height: f64,
+    rect: PixelRect,
     label: &str,
+    entity: Option<&str>,
     border_width: f64,
     font_px: f64,
-    padding_px: f64,
 ) -> Result<()> {
-    let text_width = measure_text_width(ctx, label, font_px);
-    let width = (text_width + padding_px).max(10.0);
-    let rect = PixelRect {
-        x0: x,
-        y0: y,
-        width,
-        height,
-        center: Point {
-            x: x + width / 2.0,
-            y: y + height / 2.0,
-        },
-    };
     ctx.set_line_width(border_width.max(1.0));
-    path_round_rect_impl(
-        ctx,
-        rect.x0,
-        rect.y0,
-        rect.width,
-        rect.height,
-        rect.width * 0.04,
-    )?;
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    path_unit_info(ctx, rect, entity, rect.width * 0.04)?;
+    set_color(ctx, theme::palette().paper);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     draw_text_centered(ctx, rect.center, label, font_px)?;
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
 
-/// Draw a state variable box sized from label width.
+/// Draw a state variable box in `rect`.
 fn draw_state_var(
     ctx: &CairoContext,
-    x: f64,
-    y: f64,
-    height: f64,
+    rect: PixelRect,
     label: &str,
     border_width: f64,
     font_px: f64,
-    padding_px: f64,
-    min_width: f64,
 ) -> Result<()> {
-    let text_width = measure_text_width(ctx, label, font_px);
-    let width = (text_width + padding_px).max(min_width);
-    let rect = PixelRect {
-        x0: x,
-        y0: y,
-        width,
-        height,
-        center: Point {
-            x: x + width / 2.0,
-            y: y + height / 2.0,
-        },
-    };
     ctx.set_line_width(border_width.max(1.0));
     let radius = 0.24 * rect.width.max(rect.height);
     path_round_rect_impl(ctx, rect.x0, rect.y0, rect.width, rect.height, radius)?;
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    set_color(ctx, theme::palette().paper);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     draw_text_centered(ctx, rect.center, label, font_px)?;
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
@@ -1429,18 +2441,16 @@ fn draw_state_var(
 
 /// Measure label width using the current Cairo/Pango context.
 fn measure_text_width(ctx: &CairoContext, text: &str, font_px: f64) -> f64 {
-    let layout = pangocairo::create_layout(ctx);
-    let mut font_desc = FontDescription::from_string(FONT_FAMILY);
-    font_desc.set_absolute_size(font_px * pango::SCALE as f64);
-    layout.set_font_description(Some(&font_desc));
-    layout.set_text(text);
-    let (width, _) = layout.pixel_size();
-    width as f64
+    measure_text_size(ctx, text, font_px).0
 }
 
-/// Convert an x offset in px units to the node's pixel space.
-fn px_x(rect: PixelRect, value: f64, scale_x: f64) -> f64 {
-    rect.x0 + value * scale_x
+/// Measure label width and height using the current Cairo/Pango context.
+fn measure_text_size(ctx: &CairoContext, text: &str, font_px: f64) -> (f64, f64) {
+    let layout = pangocairo::create_layout(ctx);
+    layout.set_font_description(Some(&fonts::description(font_px)));
+    markup::set_label_text(&layout, text);
+    let (width, height) = layout.pixel_size();
+    (width as f64, height as f64)
 }
 
 /// Convert a y offset in px units to the node's pixel space.
@@ -1448,6 +2458,55 @@ fn px_y(rect: PixelRect, value: f64, scale_y: f64) -> f64 {
     rect.y0 + value * scale_y
 }
 
+/// Draw an existence (half-filled circle) or location (circle with a T) marker.
+fn draw_existence_marker(
+    ctx: &CairoContext,
+    rect: PixelRect,
+    class_name: &str,
+    line_width: f64,
+) -> Result<()> {
+    let rect = square_pixel_rect(rect);
+    let radius = rect.width / 2.0;
+    ctx.set_line_width(line_width.max(1.0));
+    path_ellipse(ctx, rect)?;
+    set_color(ctx, theme::palette().paper);
+    ctx.fill_preserve()?;
+    set_color(ctx, theme::palette().border);
+    ctx.stroke()?;
+    ctx.new_path();
+    if class_name == "existence" {
+        let half_turn = std::f64::consts::PI;
+        ctx.arc(
+            rect.center.x,
+            rect.center.y,
+            radius,
+            half_turn / 2.0,
+            half_turn * 1.5,
+        );
+        ctx.close_path();
+        ctx.fill()?;
+    } else {
+        let arm = radius * 0.55;
+        ctx.move_to(rect.center.x - arm, rect.center.y - arm * 0.6);
+        ctx.line_to(rect.center.x + arm, rect.center.y - arm * 0.6);
+        ctx.move_to(rect.center.x, rect.center.y - arm * 0.6);
+        ctx.line_to(rect.center.x, rect.center.y + arm);
+        ctx.stroke()?;
+    }
+    ctx.set_line_width(DEFAULT_LINE_WIDTH);
+    Ok(())
+}
+
+/// Draw a clone marker label centered in the clone band at the bottom of the node.
+fn draw_clone_label(ctx: &CairoContext, rect: PixelRect, label: &str) -> Result<()> {
+    let band_height = rect.height * CLONE_MARKER_HEIGHT_RATIO;
+    let center = Point {
+        x: rect.center.x,
+        y: rect.y0 + rect.height - band_height / 2.0,
+    };
+    draw_text_centered(ctx, center, label, FONT_SMALL_PX)
+}
+
 fn draw_circle_bbox(
     ctx: &CairoContext,
     transform: &Transform,
@@ -1458,18 +2517,15 @@ fn draw_circle_bbox(
     let center = transform.map_point(bbox.x + bbox.w / 2.0, bbox.y + bbox.h / 2.0);
     let radius = transform.scale_scalar(bbox.w.min(bbox.h) / 2.0);
     ctx.arc(center.x, center.y, radius, 0.0, std::f64::consts::TAU);
-    ctx.set_source_rgb(
-        DEFAULT_FILL_COLOR.0,
-        DEFAULT_FILL_COLOR.1,
-        DEFAULT_FILL_COLOR.2,
-    );
+    set_color(ctx, theme::palette().fill);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     draw_text_centered(ctx, center, label, font_px)?;
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_shape_with_clone<F>(
     ctx: &CairoContext,
     rect: PixelRect,
@@ -1489,12 +2545,12 @@ where
         ctx.set_source_rgb(color.0, color.1, color.2);
         ctx.fill_preserve()?;
     }
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.stroke()?;
     if has_clone {
         draw_clone_marker(ctx, rect, &path_fn)?;
         path_fn(ctx, rect)?;
-        ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+        set_color(ctx, theme::palette().border);
         ctx.stroke()?;
     }
     draw_text_centered(ctx, rect.center, label, font_px)?;
@@ -1516,21 +2572,88 @@ where
     ctx.clip();
     ctx.new_path();
     ctx.rectangle(marker_x, marker_y, marker_width, marker_height);
-    ctx.set_source_rgb(
-        CLONE_MARKER_FILL_COLOR.0,
-        CLONE_MARKER_FILL_COLOR.1,
-        CLONE_MARKER_FILL_COLOR.2,
-    );
+    set_color(ctx, theme::palette().clone_marker_fill);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(AUX_LINE_COLOR.0, AUX_LINE_COLOR.1, AUX_LINE_COLOR.2);
+    set_color(ctx, theme::palette().aux_line);
     ctx.set_line_width(CLONE_MARKER_STROKE_WIDTH.max(1.0));
     ctx.stroke()?;
     let _ = ctx.restore();
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    set_color(ctx, theme::palette().border);
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
 
+/// Trace the outline a glyph class is drawn with, without filling or stroking it.
+fn path_glyph_outline(
+    ctx: &CairoContext,
+    rect: PixelRect,
+    class_name: &str,
+    orientation: Option<&str>,
+) -> Result<()> {
+    let class_base = class_name.strip_suffix(" multimer").unwrap_or(class_name);
+    match class_base {
+        "simple chemical" | "unspecified entity" | "source and sink" => path_ellipse(ctx, rect),
+        "macromolecule" | "unit of information" => {
+            let radius = (rect.width.min(rect.height) * 0.1).max(1.0);
+            path_round_rect(ctx, rect, radius)
+        }
+        "nucleic acid feature" => {
+            let radius = (rect.height * 0.3).max(1.0);
+            path_round_bottom_rect_impl(ctx, rect.x0, rect.y0, rect.width, rect.height, radius)
+        }
+        "complex" => {
+            let corner = (rect.width.min(rect.height) * 0.2).max(1.0);
+            path_cut_rect(ctx, rect, corner)
+        }
+        "perturbing agent" => path_concave_hexagon(ctx, rect),
+        "phenotype" | "outcome" => path_hexagon(ctx, rect),
+        "compartment" => path_barrel(ctx, rect),
+        "tag" | "terminal" => path_tag(ctx, rect, orientation),
+        "state variable" => {
+            let radius = 0.24 * rect.width.max(rect.height);
+            path_round_rect_impl(ctx, rect.x0, rect.y0, rect.width, rect.height, radius)
+        }
+        "process" | "omitted process" | "uncertain process" => {
+            path_rect(ctx, square_pixel_rect(rect))
+        }
+        "association" | "dissociation" | "and" | "or" | "not" | "equivalence" | "existence"
+        | "location" => path_ellipse(ctx, square_pixel_rect(rect)),
+        _ => path_rect(ctx, rect),
+    }
+}
+
+/// Trace a unit of information, using the entity shape when an ER `<entity>` is given.
+fn path_unit_info(
+    ctx: &CairoContext,
+    rect: PixelRect,
+    entity: Option<&str>,
+    radius: f64,
+) -> Result<()> {
+    match entity {
+        Some(
+            name @ ("simple chemical"
+            | "unspecified entity"
+            | "macromolecule"
+            | "nucleic acid feature"
+            | "complex"),
+        ) => path_glyph_outline(ctx, rect, name, None),
+        Some("perturbation") => path_concave_hexagon(ctx, rect),
+        _ => path_round_rect(ctx, rect, radius),
+    }
+}
+
+/// Shrink a rect to the largest square sharing its center.
+fn square_pixel_rect(rect: PixelRect) -> PixelRect {
+    let side = rect.width.min(rect.height);
+    PixelRect {
+        x0: rect.center.x - side / 2.0,
+        y0: rect.center.y - side / 2.0,
+        width: side,
+        height: side,
+        center: rect.center,
+    }
+}
+
 fn path_rect(ctx: &CairoContext, rect: PixelRect) -> Result<()> {
     ctx.new_path();
     ctx.rectangle(rect.x0, rect.y0, rect.width, rect.height);
@@ -1668,18 +2791,49 @@ fn path_barrel(ctx: &CairoContext, rect: PixelRect) -> Result<()> {
     Ok(())
 }
 
-fn path_tag(ctx: &CairoContext, rect: PixelRect, notch: f64) -> Result<()> {
+/// Trace a tag or terminal pointing toward `orientation` (left when unset).
+fn path_tag(ctx: &CairoContext, rect: PixelRect, orientation: Option<&str>) -> Result<()> {
     let x0 = rect.x0;
     let y0 = rect.y0;
     let x1 = rect.x0 + rect.width;
     let y1 = rect.y0 + rect.height;
+    let mid_x = (x0 + x1) / 2.0;
     let mid_y = (y0 + y1) / 2.0;
     ctx.new_path();
-    ctx.move_to(x0 + notch, y0);
-    ctx.line_to(x1, y0);
-    ctx.line_to(x1, y1);
-    ctx.line_to(x0 + notch, y1);
-    ctx.line_to(x0, mid_y);
+    match orientation {
+        Some("right") => {
+            let notch = (rect.height * 0.3).max(2.0);
+            ctx.move_to(x0, y0);
+            ctx.line_to(x1 - notch, y0);
+            ctx.line_to(x1, mid_y);
+            ctx.line_to(x1 - notch, y1);
+            ctx.line_to(x0, y1);
+        }
+        Some("up") => {
+            let notch = (rect.width * 0.3).max(2.0);
+            ctx.move_to(mid_x, y0);
+            ctx.line_to(x1, y0 + notch);
+            ctx.line_to(x1, y1);
+            ctx.line_to(x0, y1);
+            ctx.line_to(x0, y0 + notch);
+        }
+        Some("down") => {
+            let notch = (rect.width * 0.3).max(2.0);
+            ctx.move_to(x0, y0);
+            ctx.line_to(x1, y0);
+            ctx.line_to(x1, y1 - notch);
+            ctx.line_to(mid_x, y1);
+            ctx.line_to(x0, y1 - notch);
+        }
+        _ => {
+            let notch = (rect.height * 0.3).max(2.0);
+            ctx.move_to(x0 + notch, y0);
+            ctx.line_to(x1, y0);
+            ctx.line_to(x1, y1);
+            ctx.line_to(x0 + notch, y1);
+            ctx.line_to(x0, mid_y);
+        }
+    }
     ctx.close_path();
     Ok(())
 }
@@ -1781,6 +2935,7 @@ fn quad_curve_to(ctx: &CairoContext, cx: f64, cy: f64, x: f64, y: f64) -> Result
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_arc(
     ctx: &CairoContext,
     points: &[Point],
@@ -1788,18 +2943,28 @@ fn draw_arc(
     arrow_size: f64,
     bar_length: f64,
     bar_offset: f64,
+    color: (f64, f64, f64),
+    line_width: f64,
 ) -> Result<()> {
     if points.len() < 2 {
         return Ok(());
     }
 
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
-    ctx.set_line_width(DEFAULT_LINE_WIDTH);
+    ctx.set_source_rgb(color.0, color.1, color.2);
+    ctx.set_line_width(line_width);
+    let dashes = arc_dash_pattern(class_name).filter(|_| theme::current().arc_dashes);
+    // Continue the dash pattern across segments instead of restarting it at every bend.
+    let mut dash_offset = 0.0;
     for pair in points.windows(2) {
+        if let Some(dashes) = dashes {
+            ctx.set_dash(dashes, dash_offset);
+            dash_offset += (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y);
+        }
         ctx.move_to(pair[0].x, pair[0].y);
         ctx.line_to(pair[1].x, pair[1].y);
         ctx.stroke()?;
     }
+    ctx.set_dash(&[], 0.0);
 
     let end = points[points.len() - 1];
     let prev = points[points.len() - 2];
@@ -1827,9 +2992,25 @@ fn draw_arc(
         _ => {}
     }
 
+    set_color(ctx, theme::palette().border);
+    ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
 
+/// Dash pattern telling modulatory arc classes apart in print; flow arcs stay solid.
+fn arc_dash_pattern(class_name: &str) -> Option<&'static [f64]> {
+    match class_name {
+        "modulation" | "unknown influence" => Some(&[8.0, 4.0]),
+        "stimulation" | "positive influence" => Some(&[2.0, 3.0]),
+        "catalysis" => Some(&[8.0, 3.0, 2.0, 3.0]),
+        "inhibition" | "negative influence" => Some(&[4.0, 3.0]),
+        "necessary stimulation" => Some(&[8.0, 3.0, 2.0, 3.0, 2.0, 3.0]),
+        "absolute inhibition" => Some(&[4.0, 3.0, 1.0, 3.0]),
+        "absolute stimulation" => Some(&[1.0, 2.0]),
+        _ => None,
+    }
+}
+
 fn draw_open_circle(ctx: &CairoContext, center: Point, radius: f64) -> Result<()> {
     ctx.arc(
         center.x,
@@ -1850,9 +3031,10 @@ fn draw_filled_circle(ctx: &CairoContext, center: Point, radius: f64) -> Result<
         0.0,
         std::f64::consts::TAU,
     );
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    let stroke_source = ctx.source();
+    set_color(ctx, theme::palette().paper);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    ctx.set_source(&stroke_source)?;
     ctx.stroke()?;
     Ok(())
 }
@@ -1900,9 +3082,10 @@ fn draw_open_triangle_opaque(ctx: &CairoContext, end: Point, prev: Point, size:
     ctx.line_to(p2.x, p2.y);
     ctx.line_to(tip.x, tip.y);
     ctx.close_path();
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    let stroke_source = ctx.source();
+    set_color(ctx, theme::palette().paper);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    ctx.set_source(&stroke_source)?;
     ctx.stroke()?;
     Ok(())
 }
@@ -1928,9 +3111,10 @@ fn draw_open_diamond_opaque(ctx: &CairoContext, end: Point, prev: Point, size: f
     ctx.line_to(base.x, base.y);
     ctx.line_to(p2.x, p2.y);
     ctx.close_path();
-    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    let stroke_source = ctx.source();
+    set_color(ctx, theme::palette().paper);
     ctx.fill_preserve()?;
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
+    ctx.set_source(&stroke_source)?;
     ctx.stroke()?;
     Ok(())
 }
@@ -2030,11 +3214,9 @@ fn draw_text_centered(ctx: &CairoContext, center: Point, text: &str, font_px: f6
         return Ok(());
     }
     let layout = pangocairo::create_layout(ctx);
-    let mut font_desc = FontDescription::from_string(FONT_FAMILY);
-    font_desc.set_absolute_size(font_px * pango::SCALE as f64);
-    layout.set_font_description(Some(&font_desc));
+    layout.set_font_description(Some(&fonts::description(font_px)));
     layout.set_alignment(Alignment::Center);
-    layout.set_text(text);
+    markup::set_label_text(&layout, text);
 
     let (width, height) = layout.pixel_size();
     let x = center.x - width as f64 / 2.0;
@@ -2043,17 +3225,47 @@ fn draw_text_centered(ctx: &CairoContext, center: Point, text: &str, font_px: f6
     Ok(())
 }
 
+/// Draw a single line of text starting at `x`, vertically centered on `center_y`.
+fn draw_text_left(
+    ctx: &CairoContext,
+    x: f64,
+    center_y: f64,
+    text: &str,
+    font_px: f64,
+) -> Result<()> {
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let layout = pangocairo::create_layout(ctx);
+    layout.set_font_description(Some(&fonts::description(font_px)));
+    layout.set_text(text);
+
+    let (_, height) = layout.pixel_size();
+    draw_text_at(ctx, x, center_y - height as f64 / 2.0, &layout)
+}
+
 /// Draw text with an outline at the given top-left position.
 fn draw_text_at(ctx: &CairoContext, x: f64, y: f64, layout: &pango::Layout) -> Result<()> {
     ctx.move_to(x, y);
     pangocairo::layout_path(ctx, layout);
-    if TEXT_OUTLINE_WIDTH > 0.0 {
-        ctx.set_source_rgb(1.0, 1.0, 1.0);
+    if let Some(halo) = theme::current()
+        .text_halo()
+        .filter(|_| TEXT_OUTLINE_WIDTH > 0.0)
+    {
+        ctx.set_source_rgb(halo.0, halo.1, halo.2);
         ctx.set_line_width(TEXT_OUTLINE_WIDTH);
         ctx.stroke_preserve()?;
     }
-    ctx.set_source_rgb(BORDER_COLOR.0, BORDER_COLOR.1, BORDER_COLOR.2);
-    ctx.fill()?;
+    set_color(ctx, theme::palette().border);
+    if fonts::current().text_as_paths {
+        ctx.fill()?;
+    } else {
+        // Keep the halo as a path but show the text itself, so SVG and PDF embed the font
+        // and the labels stay selectable and searchable.
+        ctx.new_path();
+        ctx.move_to(x, y);
+        pangocairo::show_layout(ctx, layout);
+    }
     ctx.set_line_width(DEFAULT_LINE_WIDTH);
     Ok(())
 }
@@ -2069,11 +3281,9 @@ fn draw_text_bottom_centered(
         return Ok(());
     }
     let layout = pangocairo::create_layout(ctx);
-    let mut font_desc = FontDescription::from_string(FONT_FAMILY);
-    font_desc.set_absolute_size(font_px * pango::SCALE as f64);
-    layout.set_font_description(Some(&font_desc));
+    layout.set_font_description(Some(&fonts::description(font_px)));
     layout.set_alignment(Alignment::Center);
-    layout.set_text(text);
+    markup::set_label_text(&layout, text);
 
     let (width, height) = layout.pixel_size();
     let x = rect.center.x - width as f64 / 2.0;
@@ -2113,29 +3323,23 @@ fn state_var_label(value: Option<&str>, variable: Option<&str>) -> String {
     }
 }
 
-fn first_child_label(children: &[&Glyph], class_name: &str) -> Option<String> {
-    children
-        .iter()
-        .find(|child| child.class_name == class_name)
-        .map(|child| child.label.clone())
-        .filter(|label| !label.trim().is_empty())
+fn is_aux_class(class_name: &str) -> bool {
+    matches!(
+        class_name,
+        "unit of information" | "state variable" | "existence" | "location"
+    )
 }
 
-fn first_child_state_label(children: &[&Glyph], class_name: &str) -> Option<String> {
-    children
-        .iter()
-        .find(|child| child.class_name == class_name)
-        .map(|child| {
-            if !child.label.trim().is_empty() {
-                child.label.clone()
-            } else {
-                state_var_label(
-                    child.state_value.as_deref(),
-                    child.state_variable.as_deref(),
-                )
-            }
-        })
-        .filter(|label| !label.trim().is_empty())
+/// Label shown for an aux unit; state variables fall back to value@variable.
+fn aux_label(glyph: &Glyph) -> String {
+    if glyph.class_name == "state variable" && glyph.label.trim().is_empty() {
+        state_var_label(
+            glyph.state_value.as_deref(),
+            glyph.state_variable.as_deref(),
+        )
+    } else {
+        glyph.label.clone()
+    }
 }
 
 /// Return default widths/heights from sbgnStyle for scale reference.
@@ -2154,7 +3358,7 @@ fn default_dimensions(class_name: &str) -> Option<(f64, f64)> {
         "association" | "dissociation" => Some((25.0, 25.0)),
         "compartment" => Some((50.0, 50.0)),
         "tag" => Some((100.0, 65.0)),
-        "and" | "or" | "not" => Some((40.0, 40.0)),
+        "and" | "or" | "not" | "equivalence" => Some((40.0, 40.0)),
         _ => None,
     }
 }
@@ -2164,6 +3368,7 @@ fn glyph_font_px(class_name: &str) -> f64 {
         "state variable"
         | "unit of information"
         | "cardinality"
+        | "stoichiometry"
         | "variable value"
         | "tag"
         | "terminal" => FONT_SMALL_PX,
@@ -2171,19 +3376,29 @@ fn glyph_font_px(class_name: &str) -> f64 {
     }
 }
 
-fn parse_sbgn(doc: &Document) -> Result<(Vec<Glyph>, Vec<Arc>, Bounds)> {
-    let arc_nodes: Vec<_> = doc
+fn find_map_node<'a, 'input>(doc: &'a Document<'input>) -> Result<roxmltree::Node<'a, 'input>> {
+    doc.descendants()
+        .find(|node| node.has_tag_name("map"))
+        .ok_or_else(|| anyhow!("SBGN file missing map element"))
+}
+
+fn parse_map(map_node: &roxmltree::Node) -> Result<(Vec<Glyph>, Vec<Arc>)> {
+    // Only this map's arcs; documents with submaps hold several maps.
+    let arc_nodes: Vec<_> = map_node
         .descendants()
         .filter(|node| node.has_tag_name("arc"))
         .collect();
 
+    // Arcgroup glyphs (ER interactions, AF logic) are top-level glyphs like the others.
     let mut glyphs = Vec::new();
-    let map_node = doc
-        .descendants()
-        .find(|node| node.has_tag_name("map"))
-        .ok_or_else(|| anyhow!("SBGN file missing map element"))?;
     for glyph_node in map_node
         .children()
+        .chain(
+            map_node
+                .children()
+                .filter(|node| node.has_tag_name("arcgroup"))
+                .flat_map(|group| group.children()),
+        )
         .filter(|node| node.has_tag_name("glyph"))
     {
         parse_glyph_node(&glyph_node, None, &mut glyphs)?;
@@ -2191,41 +3406,55 @@ fn parse_sbgn(doc: &Document) -> Result<(Vec<Glyph>, Vec<Arc>, Bounds)> {
 
     let mut arcs = Vec::new();
     for arc in arc_nodes {
+        let id = arc.attribute("id").unwrap_or_default().to_string();
         let class_name = arc.attribute("class").unwrap_or_default().to_string();
-        let start = arc
-            .children()
-            .find(|node| node.has_tag_name("start"))
-            .ok_or_else(|| anyhow!("Arc missing start"))?;
-        let end = arc
-            .children()
-            .find(|node| node.has_tag_name("end"))
-            .ok_or_else(|| anyhow!("Arc missing end"))?;
+        let source = arc.attribute("source").map(|value| value.to_string());
+        let target = arc.attribute("target").map(|value| value.to_string());
+        let start = arc.children().find(|node| node.has_tag_name("start"));
+        let end = arc.children().find(|node| node.has_tag_name("end"));
 
+        // Arcs without start/end geometry are kept so the layout pass can route them.
         let mut points = Vec::new();
-        points.push(Point {
-            x: parse_f64(start.attribute("x")).ok_or_else(|| anyhow!("Bad arc start x"))?,
-            y: parse_f64(start.attribute("y")).ok_or_else(|| anyhow!("Bad arc start y"))?,
-        });
-
-        for next in arc.children().filter(|node| node.has_tag_name("next")) {
-            if let (Some(x), Some(y)) = (
-                parse_f64(next.attribute("x")),
-                parse_f64(next.attribute("y")),
-            ) {
-                points.push(Point { x, y });
+        if let (Some(start), Some(end)) = (start, end) {
+            points.push(Point {
+                x: parse_f64(start.attribute("x")).ok_or_else(|| anyhow!("Bad arc start x"))?,
+                y: parse_f64(start.attribute("y")).ok_or_else(|| anyhow!("Bad arc start y"))?,
+            });
+
+            for next in arc.children().filter(|node| node.has_tag_name("next")) {
+                if let (Some(x), Some(y)) = (
+                    parse_f64(next.attribute("x")),
+                    parse_f64(next.attribute("y")),
+                ) {
+                    points.push(Point { x, y });
+                }
             }
+
+            points.push(Point {
+                x: parse_f64(end.attribute("x")).ok_or_else(|| anyhow!("Bad arc end x"))?,
+                y: parse_f64(end.attribute("y")).ok_or_else(|| anyhow!("Bad arc end y"))?,
+            });
         }
 
-        points.push(Point {
-            x: parse_f64(end.attribute("x")).ok_or_else(|| anyhow!("Bad arc end x"))?,
-            y: parse_f64(end.attribute("y")).ok_or_else(|| anyhow!("Bad arc end y"))?,
-        });
+        // Stoichiometry and cardinality labels are glyphs nested inside the arc.
+        let mut arc_glyphs = Vec::new();
+        for child in arc.children().filter(|node| node.has_tag_name("glyph")) {
+            parse_glyph_node(&child, Some(id.clone()), &mut arc_glyphs)?;
+        }
 
-        arcs.push(Arc { class_name, points });
+        arcs.push(Arc {
+            id,
+            class_name,
+            source,
+            target,
+            points,
+            glyphs: arc_glyphs,
+            ports: parse_ports(&arc),
+            extras: unmodeled_children(&arc, &["glyph", "port", "start", "next", "end"]),
+        });
     }
 
-    let bounds = compute_bounds(&glyphs, &arcs)?;
-    Ok((glyphs, arcs, bounds))
+    Ok((glyphs, arcs))
 }
 
 fn parse_glyph_node(
@@ -2246,17 +3475,21 @@ fn parse_glyph_node(
     let bbox_node = glyph.children().find(|node| node.has_tag_name("bbox"));
     let bbox = bbox_node.and_then(|node| parse_bbox(&node));
 
-    let ports = glyph
-        .children()
-        .filter(|node| node.has_tag_name("port"))
-        .filter_map(|node| {
-            let x = parse_f64(node.attribute("x"))?;
-            let y = parse_f64(node.attribute("y"))?;
-            Some(Point { x, y })
-        })
-        .collect();
+    let ports = parse_ports(glyph);
 
-    let has_clone = glyph.children().any(|node| node.has_tag_name("clone"));
+    let clone_node = glyph.children().find(|node| node.has_tag_name("clone"));
+    let has_clone = clone_node.is_some();
+    let clone_label = clone_node
+        .and_then(|node| node.children().find(|child| child.has_tag_name("label")))
+        .and_then(|node| node.attribute("text"))
+        .map(|text| text.replace('\r', ""))
+        .filter(|text| !text.trim().is_empty());
+    // ER units of information name the entity shape to draw them with.
+    let entity = glyph
+        .children()
+        .find(|node| node.has_tag_name("entity"))
+        .and_then(|node| node.attribute("name"))
+        .map(|value| value.to_string());
     let state_node = glyph.children().find(|node| node.has_tag_name("state"));
     let state_value = state_node
         .and_then(|node| node.attribute("value"))
@@ -2267,6 +3500,10 @@ fn parse_glyph_node(
     let orientation = glyph
         .attribute("orientation")
         .map(|value| value.to_string());
+    let compartment_ref = glyph
+        .attribute("compartmentRef")
+        .map(|value| value.to_string());
+    let compartment_order = parse_f64(glyph.attribute("compartmentOrder"));
 
     let glyph_id = id.clone();
     glyphs.push(Glyph {
@@ -2277,9 +3514,32 @@ fn parse_glyph_node(
         label,
         ports,
         has_clone,
+        clone_label,
+        entity,
         state_value,
         state_variable,
         orientation,
+        compartment_ref,
+        compartment_order,
+        fill: None,
+        notes: annotations::notes_text(glyph),
+        notes_xml: child_source(glyph, "notes"),
+        xrefs: annotations::xrefs(glyph),
+        annotation_xml: child_source(glyph, "annotation"),
+        extras: unmodeled_children(
+            glyph,
+            &[
+                "notes",
+                "annotation",
+                "label",
+                "state",
+                "clone",
+                "entity",
+                "bbox",
+                "glyph",
+                "port",
+            ],
+        ),
     });
 
     for child in glyph.children().filter(|node| node.has_tag_name("glyph")) {
@@ -2288,6 +3548,35 @@ fn parse_glyph_node(
     Ok(())
 }
 
+fn parse_ports(node: &roxmltree::Node) -> Vec<Port> {
+    node.children()
+        .filter(|child| child.has_tag_name("port"))
+        .filter_map(|child| {
+            let x = parse_f64(child.attribute("x"))?;
+            let y = parse_f64(child.attribute("y"))?;
+            Some(Port {
+                id: child.attribute("id").unwrap_or_default().to_string(),
+                point: Point { x, y },
+            })
+        })
+        .collect()
+}
+
+/// Source text of the first child element named `tag`.
+fn child_source(node: &roxmltree::Node, tag: &str) -> Option<String> {
+    let child = node.children().find(|child| child.has_tag_name(tag))?;
+    Some(node.document().input_text()[child.range()].to_string())
+}
+
+/// Source text of element children whose tag is not in `known`, for lossless export.
+fn unmodeled_children(node: &roxmltree::Node, known: &[&str]) -> Vec<String> {
+    let input = node.document().input_text();
+    node.children()
+        .filter(|child| child.is_element() && !known.contains(&child.tag_name().name()))
+        .map(|child| input[child.range()].to_string())
+        .collect()
+}
+
 fn parse_bbox(node: &roxmltree::Node) -> Option<BBox> {
     Some(BBox {
         x: parse_f64(node.attribute("x"))?,
@@ -2301,9 +3590,40 @@ fn parse_f64(value: Option<&str>) -> Option<f64> {
     value.and_then(|v| v.parse::<f64>().ok())
 }
 
-fn compute_bounds(glyphs: &[Glyph], _arcs: &[Arc]) -> Result<Bounds> {
+/// Extent of everything drawn: glyphs, ports, arc paths and the boxes on arcs.
+fn compute_bounds(glyphs: &[Glyph], arcs: &[Arc]) -> Result<Bounds> {
     let mut x_values = Vec::new();
     let mut y_values = Vec::new();
+    let ctx = measuring_context()?;
+
+    for arc in arcs {
+        for point in &arc.points {
+            x_values.push(point.x);
+            y_values.push(point.y);
+        }
+        for glyph in &arc.glyphs {
+            // Boxes placed at draw time are sized in pixels; the canvas maps one unit to one
+            // pixel (see transform_with_padding), so the same size applies here.
+            let bbox = match glyph.bbox {
+                Some(bbox) => bbox,
+                None if is_arc_label(glyph) => {
+                    let Some(center) = arc_label_anchor(&arc.points, &arc.class_name) else {
+                        continue;
+                    };
+                    let (w, h) = arc_label_size(&ctx, glyph);
+                    BBox {
+                        x: center.x - w / 2.0,
+                        y: center.y - h / 2.0,
+                        w,
+                        h,
+                    }
+                }
+                None => continue,
+            };
+            x_values.extend([bbox.x, bbox.x + bbox.w]);
+            y_values.extend([bbox.y, bbox.y + bbox.h]);
+        }
+    }
 
     for glyph in glyphs {
         if let Some(bbox) = glyph.bbox {
@@ -2313,8 +3633,8 @@ fn compute_bounds(glyphs: &[Glyph], _arcs: &[Arc]) -> Result<Bounds> {
             y_values.push(bbox.y + bbox.h);
         }
         for port in &glyph.ports {
-            x_values.push(port.x);
-            y_values.push(port.y);
+            x_values.push(port.point.x);
+            y_values.push(port.point.y);
         }
     }
 
@@ -2345,3 +3665,63 @@ fn transform_with_padding(bounds: Bounds, padding: f64) -> (Transform, f64, f64)
         height,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arc(class_name: &str, points: Vec<Point>, glyphs: Vec<Glyph>) -> Arc {
+        Arc {
+            id: "arc".to_string(),
+            class_name: class_name.to_string(),
+            source: None,
+            target: None,
+            points,
+            glyphs,
+            ports: Vec::new(),
+            extras: Vec::new(),
+        }
+    }
+
+    fn node() -> Glyph {
+        let bbox = BBox {
+            x: 0.0,
+            y: 0.0,
+            w: 60.0,
+            h: 40.0,
+        };
+        Glyph::new("a", "macromolecule", Some(bbox), "A")
+    }
+
+    #[test]
+    fn bounds_include_arc_paths_and_their_boxes() {
+        let cardinality = BBox {
+            x: 150.0,
+            y: -30.0,
+            w: 20.0,
+            h: 20.0,
+        };
+        let arcs = [arc(
+            "production",
+            vec![Point { x: 60.0, y: 20.0 }, Point { x: 240.0, y: 20.0 }],
+            vec![Glyph::new("n", "cardinality", Some(cardinality), "2")],
+        )];
+        let bounds = compute_bounds(&[node()], &arcs).unwrap();
+        assert_eq!(
+            (bounds.min_x, bounds.max_x, bounds.min_y, bounds.max_y),
+            (0.0, 240.0, -30.0, 40.0)
+        );
+    }
+
+    #[test]
+    fn bounds_include_unplaced_arc_labels() {
+        let points = vec![Point { x: 0.0, y: 100.0 }, Point { x: 0.0, y: 200.0 }];
+        let label = Glyph::new("s", "stoichiometry", None, "12");
+        let bare =
+            compute_bounds(&[node()], &[arc("consumption", points.clone(), Vec::new())]).unwrap();
+        let labeled =
+            compute_bounds(&[node()], &[arc("consumption", points, vec![label])]).unwrap();
+        assert_eq!((bare.min_x, bare.max_y), (0.0, 200.0));
+        assert!(labeled.min_x < 0.0);
+    }
+}
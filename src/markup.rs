@@ -0,0 +1,455 @@
+//! Subscripts, superscripts and Greek letters in labels, written out as Pango markup.
+//!
+//! Light markup is `_2` or `_{12}` for subscripts, `^+` or `^{2+}` for superscripts and
+//! `\alpha` or `\Delta` for Greek letters. The auto mode also guesses them from plain text:
+//! trailing charges (`Ca2+`), counts in chemical formulas (`H2O`, `PI(4,5)P2`) and spelled
+//! Greek names (`IFN-gamma`, `NF-kB`).
+
+use clap::ValueEnum;
+
+use crate::fonts;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LabelMarkup {
+    /// Labels are drawn as written.
+    None,
+    /// Only explicit `_`, `^` and `\name` markup.
+    Light,
+    /// Light markup plus charges, formula counts and Greek names recognized in plain text.
+    Auto,
+}
+
+const GREEK: &[(&str, char)] = &[
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("omicron", 'ο'),
+    ("pi", 'π'),
+    ("rho", 'ρ'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+];
+
+/// Element symbols allowed in a word read as a chemical formula.
+const ELEMENTS: &[&str] = &[
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Se", "Br", "Mo", "I",
+];
+
+/// Formulas whose only count is at the end, which otherwise read like gene symbols (NOS2).
+const FORMULAS: &[&str] = &[
+    "O2", "N2", "H2", "O3", "Cl2", "CO2", "NO2", "NO3", "SO2", "SO3", "SO4", "PO4", "NH3", "NH4",
+    "CH4",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Normal,
+    Sub,
+    Sup,
+}
+
+/// A run of text in one script position.
+struct Span {
+    text: String,
+    script: Script,
+}
+
+/// Put `text` on `layout`, as markup when the label markup mode finds any.
+pub(crate) fn set_label_text(layout: &pango::Layout, text: &str) {
+    let spans = match fonts::current().label_markup {
+        LabelMarkup::None => {
+            layout.set_text(text);
+            return;
+        }
+        LabelMarkup::Light => parse_light(text),
+        LabelMarkup::Auto => parse_light(text)
+            .into_iter()
+            .flat_map(|span| match span.script {
+                Script::Normal => guess(&span.text),
+                _ => vec![span],
+            })
+            .collect(),
+    };
+    if spans.iter().all(|span| span.script == Script::Normal) {
+        let plain: String = spans.into_iter().map(|span| span.text).collect();
+        layout.set_text(&plain);
+    } else {
+        layout.set_markup(&to_pango_markup(&spans));
+    }
+}
+
+fn to_pango_markup(spans: &[Span]) -> String {
+    let mut markup = String::new();
+    for span in spans {
+        let text = pango::glib::markup_escape_text(&span.text);
+        match span.script {
+            Script::Normal => markup.push_str(&text),
+            Script::Sub => markup.push_str(&format!("<sub>{text}</sub>")),
+            Script::Sup => markup.push_str(&format!("<sup>{text}</sup>")),
+        }
+    }
+    markup
+}
+
+/// Appends text to the last span when it has the same script, so plain runs stay whole.
+fn push(spans: &mut Vec<Span>, text: &str, script: Script) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.script == script => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_string(),
+            script,
+        }),
+    }
+}
+
+/// Split explicit `_`, `^` and `\name` markup; anything that does not parse stays literal.
+fn parse_light(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '_' | '^' if i + 1 < chars.len() => {
+                let script = if c == '_' { Script::Sub } else { Script::Sup };
+                if chars[i + 1] == '{' {
+                    if let Some(close) = chars[i + 2..].iter().position(|&c| c == '}') {
+                        let inner: String = chars[i + 2..i + 2 + close].iter().collect();
+                        push(&mut spans, &replace_greek_commands(&inner), script);
+                        i += close + 3;
+                        continue;
+                    }
+                } else if !chars[i + 1].is_whitespace() {
+                    push(&mut spans, &chars[i + 1].to_string(), script);
+                    i += 2;
+                    continue;
+                }
+                push(&mut spans, &c.to_string(), Script::Normal);
+                i += 1;
+            }
+            '\\' => {
+                let name: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                match greek_letter(&name) {
+                    Some(letter) => {
+                        push(&mut spans, &letter.to_string(), Script::Normal);
+                        i += name.len() + 1;
+                    }
+                    None => {
+                        push(&mut spans, "\\", Script::Normal);
+                        i += 1;
+                    }
+                }
+            }
+            _ => {
+                push(&mut spans, &c.to_string(), Script::Normal);
+                i += 1;
+            }
+        }
+    }
+    spans
+}
+
+/// `\name` commands inside a braced sub- or superscript.
+fn replace_greek_commands(text: &str) -> String {
+    parse_light(text)
+        .into_iter()
+        .map(|span| span.text)
+        .collect()
+}
+
+/// The letter for a Greek name: lowercase for `alpha`, capital for `Alpha`.
+fn greek_letter(name: &str) -> Option<char> {
+    let lower = name.to_ascii_lowercase();
+    let (_, letter) = GREEK.iter().find(|(greek, _)| *greek == lower)?;
+    if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        letter.to_uppercase().next()
+    } else {
+        Some(*letter)
+    }
+}
+
+/// Heuristic markup for plain text, one word at a time; `/` separates words as in `Na+/K+`.
+fn guess(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !is_word_separator(c))
+            .unwrap_or(rest.len());
+        push(&mut spans, &rest[..split], Script::Normal);
+        rest = &rest[split..];
+        let end = rest.find(is_word_separator).unwrap_or(rest.len());
+        guess_word(&rest[..end], &mut spans);
+        rest = &rest[end..];
+    }
+    spans
+}
+
+fn is_word_separator(c: char) -> bool {
+    c.is_whitespace() || c == '/'
+}
+
+fn guess_word(word: &str, spans: &mut Vec<Span>) {
+    let (stem, charge) = split_charge(word);
+    if is_formula(stem) {
+        let mut after_atom = false;
+        let mut digits_start = None;
+        for (i, c) in stem.char_indices() {
+            if c.is_ascii_digit() {
+                if digits_start.is_none() && after_atom {
+                    digits_start = Some(i);
+                }
+                if digits_start.is_none() {
+                    push(spans, &c.to_string(), Script::Normal);
+                }
+                continue;
+            }
+            if let Some(start) = digits_start.take() {
+                push(spans, &stem[start..i], Script::Sub);
+            }
+            after_atom = c.is_ascii_alphabetic() || c == ')';
+            push(spans, &c.to_string(), Script::Normal);
+        }
+        if let Some(start) = digits_start {
+            push(spans, &stem[start..], Script::Sub);
+        }
+    } else {
+        push(spans, &greek_names(stem), Script::Normal);
+    }
+    push(spans, &charge.replace('-', "\u{2212}"), Script::Sup);
+}
+
+/// Split a trailing charge such as `+`, `2+` or `3-` off a word like `Ca2+` or `Cl-`.
+fn split_charge(word: &str) -> (&str, &str) {
+    let signs = word.len() - word.trim_end_matches(['+', '-']).len();
+    if signs == 0 {
+        return (word, "");
+    }
+    let sign = &word[word.len() - signs..];
+    if !(sign.chars().all(|c| c == '+') || sign.chars().all(|c| c == '-')) {
+        return (word, "");
+    }
+    let mut start = word.len() - signs;
+    // One digit of charge, when the atom before it is not itself a digit run. After an
+    // all-caps stem the digit belongs to the name, as in CD4+ or NH4+.
+    let before = &word[..start];
+    if before.ends_with(|c: char| c.is_ascii_digit())
+        && !before[..before.len() - 1].ends_with(|c: char| c.is_ascii_digit())
+        && before.contains(|c: char| c.is_ascii_lowercase())
+    {
+        start -= 1;
+    }
+    let stem = &word[..start];
+    // Charges follow a species written from a capital, so `pre-` or `anti-` are left alone.
+    if !stem.starts_with(|c: char| c.is_ascii_uppercase() || c == '[')
+        || !stem.ends_with(|c: char| c.is_ascii_alphanumeric() || c == ')' || c == ']')
+    {
+        return (word, "");
+    }
+    (stem, &word[start..])
+}
+
+/// Whether a word reads as a chemical formula with at least one count: element symbols,
+/// counts, and parenthesized groups or locants such as `(4,5)`.
+///
+/// A count only at the end reads as a gene symbol (HIF1, SOS1, NOS2) unless the word also
+/// has a two-letter element after another atom (MgCl2), or is a listed formula (CO2).
+fn is_formula(word: &str) -> bool {
+    if FORMULAS.contains(&word) {
+        return true;
+    }
+    let bytes = word.as_bytes();
+    let mut i = 0;
+    let mut atoms = 0;
+    let mut counted = false;
+    let mut interior_count = false;
+    let mut two_letter = false;
+    let mut group = false;
+    let mut after_atom = false;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        if c.is_ascii_uppercase() {
+            let two = word.get(i..i + 2).filter(|symbol| {
+                symbol.as_bytes()[1].is_ascii_lowercase() && ELEMENTS.contains(symbol)
+            });
+            let symbol_len = match two {
+                Some(_) => 2,
+                None if ELEMENTS.contains(&&word[i..i + 1]) => 1,
+                None => return false,
+            };
+            two_letter |= symbol_len == 2;
+            interior_count |= counted;
+            atoms += 1;
+            after_atom = true;
+            i += symbol_len;
+        } else if c.is_ascii_digit() {
+            let run = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+            if after_atom {
+                if c == '0' || run > 2 {
+                    return false;
+                }
+                counted = true;
+            }
+            after_atom = false;
+            i += run;
+        } else if matches!(c, '(' | ',') {
+            group |= c == '(';
+            after_atom = false;
+            i += 1;
+        } else if c == ')' {
+            after_atom = true;
+            i += 1;
+        } else {
+            return false;
+        }
+    }
+    counted && (interior_count || group || (two_letter && atoms > 1))
+}
+
+/// Spelled Greek names within a word, e.g. `IFN-gamma`, `TGF-beta1`, `NF-kB`, `IkappaB`.
+fn greek_names(word: &str) -> String {
+    let mut out = String::new();
+    let mut rest = word;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic()) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        out.push_str(&greek_run(&rest[..end]));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// One run of letters: a Greek name, possibly capitalized (`Beta`), after a capital prefix
+/// (`Ikappa`) or before a capital suffix (`kappaB`), or the conventional `kB` for kappa B.
+fn greek_run(run: &str) -> String {
+    let letter = |name: &str| {
+        GREEK
+            .iter()
+            .find(|(greek, _)| *greek == name)
+            .map(|(_, letter)| *letter)
+    };
+    // Short capitalized names are left alone so `Pi` (phosphate) keeps its meaning.
+    let capitalized = run.len() >= 4 && run[1..].chars().all(|c| c.is_ascii_lowercase());
+    if let Some(letter) = letter(run).or_else(|| {
+        capitalized
+            .then(|| letter(&run.to_ascii_lowercase()))
+            .flatten()
+    }) {
+        return letter.to_string();
+    }
+    let prefix_len = run.len()
+        - run
+            .trim_start_matches(|c: char| c.is_ascii_uppercase())
+            .len();
+    let (prefix, rest) = run.split_at(prefix_len);
+    let name_len = rest
+        .find(|c: char| c.is_ascii_uppercase())
+        .unwrap_or(rest.len());
+    let (name, suffix) = rest.split_at(name_len);
+    if name == "k" && suffix.starts_with('B') && prefix.len() <= 1 {
+        return format!("{prefix}κ{suffix}");
+    }
+    // Next to other letters only longer names count, so `Meta` or `ETAR` stay as written.
+    match letter(name).filter(|_| name.len() >= 4) {
+        Some(letter) => format!("{prefix}{letter}{suffix}"),
+        None => run.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(text: &str) -> String {
+        to_pango_markup(&parse_light(text))
+    }
+
+    fn auto(text: &str) -> String {
+        to_pango_markup(&guess(text))
+    }
+
+    #[test]
+    fn light_markup_reads_scripts_and_greek_commands() {
+        assert_eq!(light("H_2O"), "H<sub>2</sub>O");
+        assert_eq!(light("Ca^{2+}"), "Ca<sup>2+</sup>");
+        assert_eq!(light("PI(4,5)P_{2}"), "PI(4,5)P<sub>2</sub>");
+        assert_eq!(light("TNF\\alpha"), "TNFα");
+        assert_eq!(light("\\Delta_{\\beta}"), "Δ<sub>β</sub>");
+    }
+
+    #[test]
+    fn light_markup_leaves_incomplete_markup_literal() {
+        assert_eq!(light("a_{b"), "a_{b");
+        assert_eq!(light("x_ y^"), "x_ y^");
+        assert_eq!(light("C:\\path"), "C:\\path");
+        assert_eq!(light("A<B & C"), "A&lt;B &amp; C");
+    }
+
+    #[test]
+    fn auto_markup_finds_formulas_and_charges() {
+        assert_eq!(auto("H2O"), "H<sub>2</sub>O");
+        assert_eq!(auto("Ca2+"), "Ca<sup>2+</sup>");
+        assert_eq!(auto("Na+/K+"), "Na<sup>+</sup>/K<sup>+</sup>");
+        assert_eq!(auto("Cl-"), "Cl<sup>−</sup>");
+        assert_eq!(auto("PO4 3-"), "PO<sub>4</sub> 3-");
+        assert_eq!(auto("C6H12O6"), "C<sub>6</sub>H<sub>12</sub>O<sub>6</sub>");
+        assert_eq!(auto("CO2"), "CO<sub>2</sub>");
+        assert_eq!(auto("MgCl2"), "MgCl<sub>2</sub>");
+        assert_eq!(auto("NH4+"), "NH<sub>4</sub><sup>+</sup>");
+        assert_eq!(auto("PI(4,5)P2"), "PI(4,5)P<sub>2</sub>");
+    }
+
+    #[test]
+    fn auto_markup_leaves_gene_names_and_words_alone() {
+        assert_eq!(auto("P53"), "P53");
+        assert_eq!(auto("MAPK1"), "MAPK1");
+        assert_eq!(auto("HIF1"), "HIF1");
+        assert_eq!(auto("SOS1"), "SOS1");
+        assert_eq!(auto("NOS2"), "NOS2");
+        assert_eq!(auto("SH2"), "SH2");
+        assert_eq!(auto("Cd4"), "Cd4");
+        for gene in ["CHK1", "NFKB1", "CCNB1", "SKP1", "PCK1", "HNF4"] {
+            assert_eq!(auto(gene), gene);
+        }
+        assert_eq!(auto("CD4+"), "CD4<sup>+</sup>");
+        assert_eq!(auto("anti-"), "anti-");
+        assert_eq!(auto("ATP"), "ATP");
+        assert_eq!(auto("Pi"), "Pi");
+    }
+
+    #[test]
+    fn auto_markup_spells_out_greek_names() {
+        assert_eq!(auto("IFN-gamma"), "IFN-γ");
+        assert_eq!(auto("TGF-beta1"), "TGF-β1");
+        assert_eq!(auto("NF-kB"), "NF-κB");
+        assert_eq!(auto("IkappaB"), "IκB");
+        assert_eq!(auto("Beta catenin"), "β catenin");
+    }
+}
@@ -0,0 +1,365 @@
+//! Export of the processed map as a plain network for Cytoscape and igraph.
+//!
+//! Every non-auxiliary glyph becomes a node and every arc with both ends an edge. Arcs that
+//! end on a port are attached to the port's glyph. Aux units are folded into their glyph's
+//! node, and nesting (complex members, compartment membership) becomes the node's parent.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::compartments::compartment_membership;
+use crate::layout::{glyph_index, is_container};
+use crate::{aux_label, is_aux_class, xml_escape, Arc, Glyph};
+
+/// A glyph as exported: its parent node and the labels of its aux units.
+struct Node<'a> {
+    glyph: &'a Glyph,
+    parent: Option<&'a str>,
+    aux: Vec<String>,
+}
+
+/// An arc whose ends resolved to exported nodes.
+struct Edge<'a> {
+    arc: &'a Arc,
+    source: &'a str,
+    target: &'a str,
+}
+
+/// Write glyphs and arcs as Cytoscape.js JSON elements, positioned at their bbox centers.
+pub(crate) fn write_cytoscape_json(path: &Path, glyphs: &[Glyph], arcs: &[Arc]) -> Result<()> {
+    let (nodes, edges) = network(glyphs, arcs);
+
+    let nodes: Vec<Value> = nodes
+        .iter()
+        .map(|node| {
+            let glyph = node.glyph;
+            let mut data = json!({
+                "id": glyph.id,
+                "label": glyph.label,
+                "class": glyph.class_name,
+            });
+            if let Some(parent) = node.parent {
+                data["parent"] = json!(parent);
+            }
+            if !node.aux.is_empty() {
+                data["auxiliary"] = json!(node.aux);
+            }
+            if glyph.has_clone {
+                data["clone"] = json!(true);
+            }
+            match glyph.bbox {
+                Some(bbox) => {
+                    data["width"] = json!(bbox.w);
+                    data["height"] = json!(bbox.h);
+                    json!({
+                        "data": data,
+                        "position": {"x": bbox.x + bbox.w / 2.0, "y": bbox.y + bbox.h / 2.0},
+                    })
+                }
+                None => json!({ "data": data }),
+            }
+        })
+        .collect();
+    let edges: Vec<Value> = edges
+        .iter()
+        .map(|edge| {
+            let mut data = json!({
+                "id": edge.arc.id,
+                "source": edge.source,
+                "target": edge.target,
+                "class": edge.arc.class_name,
+            });
+            if let Some(cardinality) = cardinality(edge.arc) {
+                data["cardinality"] = json!(cardinality);
+            }
+            json!({ "data": data })
+        })
+        .collect();
+
+    let elements = json!({ "elements": { "nodes": nodes, "edges": edges } });
+    let mut out = serde_json::to_string_pretty(&elements).context("Failed to encode JSON")?;
+    out.push('\n');
+    fs::write(path, out).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Write glyphs and arcs as GraphML with yFiles node geometry and edge paths.
+///
+/// The graph is kept flat so igraph and networkx can read it; nesting is the `parent` attribute.
+pub(crate) fn write_graphml(path: &Path, glyphs: &[Glyph], arcs: &[Arc]) -> Result<()> {
+    let (nodes, edges) = network(glyphs, arcs);
+    let centers: HashMap<&str, (f64, f64)> = nodes
+        .iter()
+        .filter_map(|node| {
+            let bbox = node.glyph.bbox?;
+            Some((
+                node.glyph.id.as_str(),
+                (bbox.x + bbox.w / 2.0, bbox.y + bbox.h / 2.0),
+            ))
+        })
+        .collect();
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    out.push_str(
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" \
+         xmlns:y=\"http://www.yworks.com/xml/graphml\">\n",
+    );
+    for (id, target, name) in [
+        ("label", "node", "label"),
+        ("class", "node", "class"),
+        ("parent", "node", "parent"),
+        ("auxiliary", "node", "auxiliary"),
+        ("edge_class", "edge", "class"),
+        ("cardinality", "edge", "cardinality"),
+    ] {
+        let _ = writeln!(
+            out,
+            "  <key id=\"{id}\" for=\"{target}\" attr.name=\"{name}\" attr.type=\"string\"/>"
+        );
+    }
+    out.push_str("  <key id=\"graphics\" for=\"node\" yfiles.type=\"nodegraphics\"/>\n");
+    out.push_str("  <key id=\"edge_graphics\" for=\"edge\" yfiles.type=\"edgegraphics\"/>\n");
+    out.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+
+    for node in &nodes {
+        let glyph = node.glyph;
+        let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&glyph.id));
+        write_data(&mut out, "label", &glyph.label);
+        write_data(&mut out, "class", &glyph.class_name);
+        if let Some(parent) = node.parent {
+            write_data(&mut out, "parent", parent);
+        }
+        if !node.aux.is_empty() {
+            write_data(&mut out, "auxiliary", &node.aux.join("; "));
+        }
+        if let Some(bbox) = glyph.bbox {
+            out.push_str("      <data key=\"graphics\"><y:ShapeNode>");
+            let _ = write!(
+                out,
+                "<y:Geometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                bbox.x, bbox.y, bbox.w, bbox.h
+            );
+            let _ = write!(
+                out,
+                "<y:Fill color=\"#FFFFFF\" transparent=\"{}\"/>",
+                is_container(glyph)
+            );
+            let _ = write!(
+                out,
+                "<y:NodeLabel>{}</y:NodeLabel><y:Shape type=\"{}\"/>",
+                xml_escape(&glyph.label),
+                yfiles_shape(&glyph.class_name)
+            );
+            out.push_str("</y:ShapeNode></data>\n");
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for edge in &edges {
+        let arc = edge.arc;
+        let _ = writeln!(
+            out,
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+            xml_escape(&arc.id),
+            xml_escape(edge.source),
+            xml_escape(edge.target)
+        );
+        write_data(&mut out, "edge_class", &arc.class_name);
+        if let Some(cardinality) = cardinality(arc) {
+            write_data(&mut out, "cardinality", cardinality);
+        }
+        out.push_str("      <data key=\"edge_graphics\"><y:PolyLineEdge>");
+        // yFiles stores path ends as offsets from the node centers.
+        let ends = (
+            arc.points.first().zip(centers.get(edge.source)),
+            arc.points.last().zip(centers.get(edge.target)),
+        );
+        if let (Some((start, source)), Some((end, target))) = ends {
+            let _ = write!(
+                out,
+                "<y:Path sx=\"{}\" sy=\"{}\" tx=\"{}\" ty=\"{}\">",
+                start.x - source.0,
+                start.y - source.1,
+                end.x - target.0,
+                end.y - target.1
+            );
+            let bends = arc.points.len().saturating_sub(1);
+            for point in arc.points.iter().take(bends).skip(1) {
+                let _ = write!(out, "<y:Point x=\"{}\" y=\"{}\"/>", point.x, point.y);
+            }
+            out.push_str("</y:Path>");
+        }
+        let _ = write!(
+            out,
+            "<y:Arrows source=\"none\" target=\"{}\"/>",
+            yfiles_arrow(&arc.class_name)
+        );
+        out.push_str("</y:PolyLineEdge></data>\n");
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    fs::write(path, out).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn network<'a>(glyphs: &'a [Glyph], arcs: &'a [Arc]) -> (Vec<Node<'a>>, Vec<Edge<'a>>) {
+    let membership = compartment_membership(glyphs);
+    let index = glyph_index(glyphs);
+
+    let mut aux: HashMap<&str, Vec<String>> = HashMap::new();
+    for glyph in glyphs
+        .iter()
+        .filter(|glyph| is_aux_class(&glyph.class_name))
+    {
+        if let Some(parent_id) = glyph.parent_id.as_deref() {
+            let label = aux_label(glyph);
+            if !label.trim().is_empty() {
+                aux.entry(parent_id).or_default().push(label);
+            }
+        }
+    }
+
+    let nodes = glyphs
+        .iter()
+        .filter(|glyph| !is_aux_class(&glyph.class_name) && !glyph.id.is_empty())
+        .map(|glyph| Node {
+            glyph,
+            parent: glyph
+                .parent_id
+                .as_deref()
+                .filter(|parent| index.contains_key(*parent))
+                .or_else(|| {
+                    let compartment = index.get(membership.get(&glyph.id)?)?;
+                    Some(glyphs[*compartment].id.as_str())
+                }),
+            aux: aux.remove(glyph.id.as_str()).unwrap_or_default(),
+        })
+        .collect();
+
+    let node_id = |id: Option<&String>| -> Option<&'a str> {
+        let glyph = &glyphs[*index.get(id?.as_str())?];
+        (!is_aux_class(&glyph.class_name)).then_some(glyph.id.as_str())
+    };
+    let edges = arcs
+        .iter()
+        .filter_map(|arc| {
+            Some(Edge {
+                arc,
+                source: node_id(arc.source.as_ref())?,
+                target: node_id(arc.target.as_ref())?,
+            })
+        })
+        .collect();
+    (nodes, edges)
+}
+
+fn cardinality(arc: &Arc) -> Option<&str> {
+    arc.glyphs
+        .iter()
+        .find(|glyph| glyph.class_name == "cardinality")
+        .map(|glyph| glyph.label.as_str())
+        .filter(|label| !label.is_empty())
+}
+
+fn write_data(out: &mut String, key: &str, value: &str) {
+    let _ = writeln!(
+        out,
+        "      <data key=\"{key}\">{}</data>",
+        xml_escape(value)
+    );
+}
+
+/// Closest yFiles shape for an SBGN glyph class.
+fn yfiles_shape(class_name: &str) -> &'static str {
+    match class_name {
+        "simple chemical"
+        | "simple chemical multimer"
+        | "unspecified entity"
+        | "source and sink"
+        | "association"
+        | "dissociation"
+        | "and"
+        | "or"
+        | "not"
+        | "equivalence" => "ellipse",
+        "complex" | "complex multimer" => "octagon",
+        "phenotype" => "hexagon",
+        "perturbing agent" => "parallelogram",
+        "process" | "omitted process" | "uncertain process" | "tag" | "submap" | "terminal" => {
+            "rectangle"
+        }
+        _ => "roundrectangle",
+    }
+}
+
+/// yFiles arrowhead closest to the SBGN arc decoration.
+fn yfiles_arrow(class_name: &str) -> &'static str {
+    match class_name {
+        "consumption" | "logic arc" | "equivalence arc" => "none",
+        "catalysis" => "transparent_circle",
+        "inhibition" | "absolute inhibition" => "t_shape",
+        "modulation" => "white_diamond",
+        "stimulation" | "necessary stimulation" | "absolute stimulation" => "white_delta",
+        _ => "standard",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::BBox;
+
+    fn glyph(id: &str, class_name: &str, label: &str) -> Glyph {
+        let bbox = BBox {
+            x: 0.0,
+            y: 0.0,
+            w: 40.0,
+            h: 20.0,
+        };
+        Glyph::new(id, class_name, Some(bbox), label)
+    }
+
+    fn temp_path(extension: &str) -> PathBuf {
+        static RUN: AtomicUsize = AtomicUsize::new(0);
+        std::env::temp_dir().join(format!(
+            "network-test-{}-{}.{extension}",
+            std::process::id(),
+            RUN.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    #[test]
+    fn complex_multimer_is_a_transparent_container() {
+        let mut member = glyph("m1", "macromolecule", "RAF1");
+        member.parent_id = Some("cx".to_string());
+        let glyphs = vec![glyph("cx", "complex multimer", ""), member];
+        let path = temp_path("graphml");
+        write_graphml(&path, &glyphs, &[]).unwrap();
+        let out = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let container = out.split("<node id=\"m1\">").next().unwrap();
+        assert!(container.contains("transparent=\"true\""));
+        assert!(out.contains("<data key=\"parent\">cx</data>"));
+    }
+
+    #[test]
+    fn cytoscape_json_escapes_labels() {
+        let glyphs = vec![glyph("g1", "macromolecule", "MAPK\n\"1\"")];
+        let path = temp_path("json");
+        write_cytoscape_json(&path, &glyphs, &[]).unwrap();
+        let out = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let value: Value = serde_json::from_str(&out).unwrap();
+        let node = &value["elements"]["nodes"][0];
+        assert_eq!(node["data"]["label"], "MAPK\n\"1\"");
+        assert_eq!(node["position"]["x"], 20.0);
+    }
+}
@@ -0,0 +1,299 @@
+//! Data overlays: a table of values per identifier, drawn as entity pool fill colors.
+//!
+//! The first column names glyphs (resolved like `--highlight` queries) and every other column
+//! holds one value per row, e.g. one column per time point. Colors come from a single scale
+//! over the whole table so frames of a time series are comparable.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::resolve::{self, Synonyms};
+use crate::theme::{self, mix, PaletteMode, Theme};
+use crate::{Arc, Glyph};
+
+const SCALE_LOW_COLOR: (f64, f64, f64) = (0.23, 0.30, 0.75);
+const SCALE_HIGH_COLOR: (f64, f64, f64) = (0.71, 0.02, 0.15);
+/// Okabe-Ito blue and vermillion.
+const COLORBLIND_LOW_COLOR: (f64, f64, f64) = (0.0, 0.447, 0.698);
+const COLORBLIND_HIGH_COLOR: (f64, f64, f64) = (0.835, 0.369, 0.0);
+
+/// Fill colors by glyph id, overriding the class default.
+pub(crate) type Fills = HashMap<String, (f64, f64, f64)>;
+
+pub(crate) struct Overlay {
+    /// Column headers after the identifier column.
+    pub(crate) columns: Vec<String>,
+    rows: Vec<Row>,
+    /// Column drawn in the PNG, SVG and PDF outputs.
+    pub(crate) column: usize,
+    scale: ColorScale,
+}
+
+struct Row {
+    query: String,
+    values: Vec<Option<f64>>,
+}
+
+impl Overlay {
+    /// Read a tab- or comma-separated table; `column` picks the value column for still images.
+    pub(crate) fn load(path: &Path, column: Option<&str>) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read overlay table {:?}", path))?;
+        Self::parse(&text, path, column)
+    }
+
+    /// Parse the text of the table read from `path`.
+    fn parse(text: &str, path: &Path, column: Option<&str>) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'));
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow!("Overlay table {:?} is empty", path))?;
+        let separator = if header.contains('\t') { '\t' } else { ',' };
+        let columns: Vec<String> = header
+            .split(separator)
+            .skip(1)
+            .map(|cell| cell.trim().to_string())
+            .collect();
+        if columns.is_empty() {
+            return Err(anyhow!(
+                "Overlay table {:?} has no value columns after the identifier column",
+                path
+            ));
+        }
+        let rows: Vec<Row> = lines
+            .map(|line| {
+                let mut cells = line.split(separator).map(str::trim);
+                let query = cells.next().unwrap_or_default().to_string();
+                let mut values: Vec<Option<f64>> = cells
+                    .map(|cell| cell.parse::<f64>().ok().filter(|value| value.is_finite()))
+                    .collect();
+                values.resize(columns.len(), None);
+                Row { query, values }
+            })
+            .filter(|row| !row.query.is_empty())
+            .collect();
+        let column = match column {
+            Some(name) => columns
+                .iter()
+                .position(|candidate| candidate == name)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Overlay column {:?} not found, expected one of: {}",
+                        name,
+                        columns.join(", ")
+                    )
+                })?,
+            None => 0,
+        };
+        let scale = ColorScale::new(rows.iter().flat_map(|row| row.values.iter().flatten()));
+        Ok(Self {
+            columns,
+            rows,
+            column,
+            scale,
+        })
+    }
+
+    /// Glyph ids named by each row; queries that need attention are reported when `report`.
+    pub(crate) fn resolve(
+        &self,
+        glyphs: &[Glyph],
+        arcs: &[Arc],
+        synonyms: &Synonyms,
+        report: bool,
+    ) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let resolution = resolve::resolve([&row.query], glyphs, arcs, synonyms);
+                if report {
+                    resolution.report("--overlay");
+                }
+                let mut ids: Vec<String> = resolution.ids.into_iter().collect();
+                ids.sort();
+                ids
+            })
+            .collect()
+    }
+
+    /// Fill colors for one column, given the ids each row resolved to.
+    pub(crate) fn fills(&self, column: usize, row_ids: &[Vec<String>]) -> Fills {
+        let mut fills = Fills::new();
+        for (row, ids) in self.rows.iter().zip(row_ids) {
+            let Some(value) = row.values[column] else {
+                continue;
+            };
+            let color = self.scale.color(value);
+            for id in ids {
+                fills.insert(id.clone(), color);
+            }
+        }
+        fills
+    }
+}
+
+/// Sequential scale for tables of one sign, diverging around zero otherwise.
+///
+/// Grayscale has no second hue for a diverging scale, so it always ramps from the glyph fill
+/// at the lowest value to the border gray at the highest.
+struct ColorScale {
+    min: f64,
+    max: f64,
+}
+
+impl ColorScale {
+    fn new<'a>(values: impl Iterator<Item = &'a f64>) -> Self {
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(*value), max.max(*value))
+        });
+        if min > max {
+            return Self { min: 0.0, max: 1.0 };
+        }
+        Self { min, max }
+    }
+
+    fn color(&self, value: f64) -> (f64, f64, f64) {
+        self.color_in(theme::current(), value)
+    }
+
+    /// Values at zero (or the weak end of a one-signed table) get the theme's glyph fill.
+    fn color_in(&self, theme: &Theme, value: f64) -> (f64, f64, f64) {
+        let neutral = theme.palette.fill;
+        let (low, high) = match theme.mode {
+            PaletteMode::Color => (SCALE_LOW_COLOR, SCALE_HIGH_COLOR),
+            PaletteMode::Colorblind => (COLORBLIND_LOW_COLOR, COLORBLIND_HIGH_COLOR),
+            PaletteMode::Grayscale => {
+                let span = (self.max - self.min).max(f64::EPSILON);
+                return mix(neutral, theme.palette.border, (value - self.min) / span);
+            }
+        };
+        if self.min < 0.0 && self.max > 0.0 {
+            let extent = self.min.abs().max(self.max);
+            let t = (value / extent).clamp(-1.0, 1.0);
+            if t < 0.0 {
+                mix(neutral, low, -t)
+            } else {
+                mix(neutral, high, t)
+            }
+        } else if self.max <= 0.0 {
+            // All values negative: the most negative is the strongest color.
+            let span = (self.max - self.min).max(f64::EPSILON);
+            mix(neutral, low, (self.max - value) / span)
+        } else {
+            let span = (self.max - self.min).max(f64::EPSILON);
+            mix(neutral, high, (value - self.min) / span)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::ThemeName;
+
+    fn table(text: &str, column: Option<&str>) -> Result<Overlay> {
+        Overlay::parse(text, Path::new("table.tsv"), column)
+    }
+
+    fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(
+            close(actual.0, expected.0)
+                && close(actual.1, expected.1)
+                && close(actual.2, expected.2),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn theme(mode: PaletteMode) -> Theme {
+        Theme::new(ThemeName::Light, mode, None, false)
+    }
+
+    #[test]
+    fn load_detects_the_separator_and_skips_unusable_cells() {
+        let overlay = table(
+            "# expression\nid,t0,t1\nMEK,1.5,x\n\nATP,,2\nRAF,NaN\n,3,4\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(overlay.columns, ["t0", "t1"]);
+        let rows: Vec<_> = overlay
+            .rows
+            .iter()
+            .map(|row| (row.query.as_str(), row.values.clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("MEK", vec![Some(1.5), None]),
+                ("ATP", vec![None, Some(2.0)]),
+                ("RAF", vec![None, None]),
+            ]
+        );
+
+        let overlay = table("id\tt0\tt1\nMEK\t1\t2\n", Some("t1")).unwrap();
+        assert_eq!(overlay.column, 1);
+        assert_eq!(overlay.rows[0].values, [Some(1.0), Some(2.0)]);
+    }
+
+    #[test]
+    fn load_rejects_unknown_columns_and_tables_without_values() {
+        let error = table("id\tt0\nMEK\t1\n", Some("t9")).err().unwrap();
+        assert!(error.to_string().contains("expected one of: t0"));
+        assert!(table("id\nMEK\n", None).is_err());
+        assert!(table("# only a comment\n", None).is_err());
+    }
+
+    #[test]
+    fn fills_color_every_id_a_row_resolved_to() {
+        let overlay = table("id\tt0\nMEK\t1\nATP\t\n", None).unwrap();
+        let fills = overlay.fills(
+            0,
+            &[
+                vec!["m1".to_string(), "m2".to_string()],
+                vec!["a".to_string()],
+            ],
+        );
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills["m1"], fills["m2"]);
+    }
+
+    #[test]
+    fn one_signed_scale_runs_from_the_fill_to_one_hue() {
+        let light = theme(PaletteMode::Color);
+        let scale = ColorScale::new([2.0, 4.0].iter());
+        assert_close(scale.color_in(&light, 2.0), light.palette.fill);
+        assert_close(scale.color_in(&light, 4.0), SCALE_HIGH_COLOR);
+        let negative = ColorScale::new([-4.0, -2.0].iter());
+        assert_close(negative.color_in(&light, -2.0), light.palette.fill);
+        assert_close(negative.color_in(&light, -4.0), SCALE_LOW_COLOR);
+    }
+
+    #[test]
+    fn diverging_scale_is_centred_on_zero() {
+        let light = theme(PaletteMode::Color);
+        let scale = ColorScale::new([-1.0, 4.0].iter());
+        assert_close(scale.color_in(&light, 0.0), light.palette.fill);
+        assert_close(scale.color_in(&light, 4.0), SCALE_HIGH_COLOR);
+        // The shorter side is scaled by the longer one, so -1 is only a quarter-strength blue.
+        assert_close(
+            scale.color_in(&light, -1.0),
+            mix(light.palette.fill, SCALE_LOW_COLOR, 0.25),
+        );
+        let colorblind = theme(PaletteMode::Colorblind);
+        assert_close(scale.color_in(&colorblind, 4.0), COLORBLIND_HIGH_COLOR);
+    }
+
+    #[test]
+    fn grayscale_scale_ramps_from_fill_to_border() {
+        let gray = theme(PaletteMode::Grayscale);
+        let scale = ColorScale::new([-1.0, 3.0].iter());
+        assert_close(scale.color_in(&gray, -1.0), gray.palette.fill);
+        assert_close(scale.color_in(&gray, 3.0), gray.palette.border);
+    }
+}
@@ -0,0 +1,188 @@
+//! Title, caption, scale bar and attribution on bands added above and below the map.
+
+use anyhow::Result;
+use cairo::Context as CairoContext;
+use pango::{Alignment, WrapMode};
+use pangocairo::functions as pangocairo;
+
+use crate::{draw_text_at, fonts, measuring_context, set_color, theme, FONT_SMALL_PX};
+
+const PAGE_MARGIN_PX: f64 = 10.0;
+const TITLE_FONT_PX: f64 = 28.0;
+const CAPTION_FONT_PX: f64 = 14.0;
+const ATTRIBUTION_FONT_PX: f64 = FONT_SMALL_PX;
+/// Space between the caption, scale bar and attribution lines of the bottom band.
+const FOOTER_GAP_PX: f64 = 6.0;
+/// Longest the scale bar may get; its length is rounded down to 1, 2 or 5 times a power of ten.
+const SCALE_BAR_MAX_PX: f64 = 120.0;
+const SCALE_BAR_TICK_PX: f64 = 8.0;
+const SCALE_BAR_LINE_WIDTH: f64 = 1.5;
+/// Space between the end of the bar and its label.
+const SCALE_BAR_LABEL_GAP_PX: f64 = 6.0;
+
+pub(crate) struct PageText {
+    pub(crate) title: Option<String>,
+    pub(crate) caption: Option<String>,
+    pub(crate) attribution: Option<String>,
+    pub(crate) scale_bar: bool,
+}
+
+impl PageText {
+    pub(crate) fn none() -> Self {
+        Self {
+            title: None,
+            caption: None,
+            attribution: None,
+            scale_bar: false,
+        }
+    }
+
+    /// Heights of the bands above and below the map on a page `width` wide.
+    pub(crate) fn bands(&self, width: f64) -> Result<(f64, f64)> {
+        let ctx = measuring_context()?;
+        let height = |text: &Option<String>, font_px: f64, bold: bool| {
+            text.as_deref()
+                .map(|text| text_layout(&ctx, text, font_px, bold, width).pixel_size().1 as f64)
+        };
+        let top = height(&self.title, TITLE_FONT_PX, true)
+            .map_or(0.0, |title| title + 2.0 * PAGE_MARGIN_PX);
+        let scale_bar = self.scale_bar.then(|| scale_bar_height(&ctx, width));
+        let footer: Vec<f64> = [
+            height(&self.caption, CAPTION_FONT_PX, false),
+            scale_bar,
+            height(&self.attribution, ATTRIBUTION_FONT_PX, false),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let bottom = if footer.is_empty() {
+            0.0
+        } else {
+            footer.iter().sum::<f64>()
+                + (footer.len() - 1) as f64 * FOOTER_GAP_PX
+                + 2.0 * PAGE_MARGIN_PX
+        };
+        Ok((top, bottom))
+    }
+
+    /// Draw the title at the top of a page and the caption, scale bar and attribution at its
+    /// bottom; `scale` is the pixels per map unit of the drawing.
+    pub(crate) fn draw(
+        &self,
+        ctx: &CairoContext,
+        width: f64,
+        height: f64,
+        scale: f64,
+    ) -> Result<()> {
+        if let Some(title) = &self.title {
+            let layout = text_layout(ctx, title, TITLE_FONT_PX, true, width);
+            draw_text_at(ctx, PAGE_MARGIN_PX, PAGE_MARGIN_PX, &layout)?;
+        }
+        let mut y = height - PAGE_MARGIN_PX;
+        if let Some(attribution) = &self.attribution {
+            let layout = text_layout(ctx, attribution, ATTRIBUTION_FONT_PX, false, width);
+            layout.set_alignment(Alignment::Right);
+            y -= layout.pixel_size().1 as f64;
+            draw_text_at(ctx, PAGE_MARGIN_PX, y, &layout)?;
+            y -= FOOTER_GAP_PX;
+        }
+        if self.scale_bar {
+            y -= scale_bar_height(ctx, width);
+            draw_scale_bar(ctx, PAGE_MARGIN_PX, y, width, scale)?;
+            y -= FOOTER_GAP_PX;
+        }
+        if let Some(caption) = &self.caption {
+            let layout = text_layout(ctx, caption, CAPTION_FONT_PX, false, width);
+            y -= layout.pixel_size().1 as f64;
+            draw_text_at(ctx, PAGE_MARGIN_PX, y, &layout)?;
+        }
+        Ok(())
+    }
+}
+
+/// Map length shown by the scale bar: the longest 1, 2 or 5 times a power of ten that fits.
+fn scale_bar_length(scale: f64) -> f64 {
+    let max_units = SCALE_BAR_MAX_PX / scale.max(f64::MIN_POSITIVE);
+    let magnitude = 10f64.powf(max_units.log10().floor());
+    [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|length| *length <= max_units)
+        .unwrap_or(magnitude)
+}
+
+fn scale_bar_label(length: f64) -> String {
+    format!("{length} units")
+}
+
+fn scale_bar_height(ctx: &CairoContext, page_width: f64) -> f64 {
+    // The widest label has the same height as any other, so a fixed sample measures the row.
+    let label = text_layout(ctx, "0 units", ATTRIBUTION_FONT_PX, false, page_width);
+    (label.pixel_size().1 as f64).max(SCALE_BAR_TICK_PX)
+}
+
+/// A bar with end ticks along the bottom of a row starting at `x`, `y`, labeled to its right.
+fn draw_scale_bar(ctx: &CairoContext, x: f64, y: f64, page_width: f64, scale: f64) -> Result<()> {
+    let length = scale_bar_length(scale);
+    let bar_px = length * scale;
+    let row = scale_bar_height(ctx, page_width);
+    let base = y + row / 2.0 + SCALE_BAR_TICK_PX / 2.0;
+    set_color(ctx, theme::palette().border);
+    ctx.set_line_width(SCALE_BAR_LINE_WIDTH);
+    ctx.move_to(x, base - SCALE_BAR_TICK_PX);
+    ctx.line_to(x, base);
+    ctx.line_to(x + bar_px, base);
+    ctx.line_to(x + bar_px, base - SCALE_BAR_TICK_PX);
+    ctx.stroke()?;
+    let label = text_layout(
+        ctx,
+        &scale_bar_label(length),
+        ATTRIBUTION_FONT_PX,
+        false,
+        page_width,
+    );
+    let label_y = y + (row - label.pixel_size().1 as f64) / 2.0;
+    draw_text_at(ctx, x + bar_px + SCALE_BAR_LABEL_GAP_PX, label_y, &label)
+}
+
+/// A layout wrapped to the page width inside the margins.
+fn text_layout(
+    ctx: &CairoContext,
+    text: &str,
+    font_px: f64,
+    bold: bool,
+    page_width: f64,
+) -> pango::Layout {
+    let layout = pangocairo::create_layout(ctx);
+    let mut font_desc = fonts::description(font_px);
+    if bold {
+        font_desc.set_weight(pango::Weight::Bold);
+    }
+    layout.set_font_description(Some(&font_desc));
+    let wrap_width = (page_width - 2.0 * PAGE_MARGIN_PX).max(1.0);
+    layout.set_width((wrap_width * pango::SCALE as f64) as i32);
+    layout.set_wrap(WrapMode::WordChar);
+    layout.set_text(text);
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_bar_length_is_a_round_number_that_fits() {
+        assert_eq!(scale_bar_length(1.0), 100.0);
+        assert_eq!(scale_bar_length(0.5), 200.0);
+        assert_eq!(scale_bar_length(0.2), 500.0);
+        assert_eq!(scale_bar_length(3.0), 20.0);
+        assert_eq!(scale_bar_length(100.0), 1.0);
+        assert_eq!(scale_bar_length(200.0), 0.5);
+    }
+
+    #[test]
+    fn scale_bar_label_drops_trailing_zeros() {
+        assert_eq!(scale_bar_label(100.0), "100 units");
+        assert_eq!(scale_bar_label(0.5), "0.5 units");
+    }
+}
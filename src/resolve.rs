@@ -0,0 +1,304 @@
+//! Resolve user-supplied identifiers (glyph ids, labels, database ids, synonyms) to glyph ids.
+//!
+//! A query matches, in order of preference: a glyph or arc id exactly, or a port id, which
+//! stands for the glyph owning the port; glyph labels,
+//! ignoring case and whitespace; cross-references from glyph annotations; and, when none of
+//! those match, the other names in its synonym table rows. Clones and multimers share a label,
+//! so matching several of them is expected; matches that span different entities are reported
+//! as ambiguous.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::annotations::Xref;
+use crate::{is_aux_class, Arc, Glyph};
+
+/// Groups of interchangeable names, one group per line of a tab-separated file.
+pub(crate) struct Synonyms {
+    groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    pub(crate) fn none() -> Self {
+        Self { groups: Vec::new() }
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read synonyms from {:?}", path))?;
+        let groups = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split('\t')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|group| group.len() > 1)
+            .collect();
+        Ok(Self { groups })
+    }
+
+    /// Other names sharing a row with `name`.
+    fn alternatives(&self, name: &str) -> Vec<&str> {
+        let key = normalize(name);
+        self.groups
+            .iter()
+            .filter(|group| group.iter().any(|entry| normalize(entry) == key))
+            .flatten()
+            .map(String::as_str)
+            .filter(|entry| normalize(entry) != key)
+            .collect()
+    }
+}
+
+/// Glyph and arc ids matched by a set of queries, with the queries that need attention.
+pub(crate) struct Resolution {
+    pub(crate) ids: HashSet<String>,
+    unmatched: Vec<String>,
+    /// Queries matching more than one entity, with the ids they matched.
+    ambiguous: Vec<(String, Vec<String>)>,
+}
+
+impl Resolution {
+    /// Print unmatched and ambiguous queries to stderr, naming the option they came from.
+    pub(crate) fn report(&self, option: &str) {
+        for query in &self.unmatched {
+            eprintln!("warning: {option} {query:?} matches no glyph");
+        }
+        for (query, ids) in &self.ambiguous {
+            eprintln!(
+                "warning: {option} {query:?} is ambiguous, using all of: {}",
+                ids.join(", ")
+            );
+        }
+    }
+}
+
+/// Resolve every query against the map; ambiguous queries keep all their matches.
+pub(crate) fn resolve<'a>(
+    queries: impl IntoIterator<Item = &'a String>,
+    glyphs: &[Glyph],
+    arcs: &[Arc],
+    synonyms: &Synonyms,
+) -> Resolution {
+    let mut queries: Vec<&String> = queries.into_iter().collect();
+    queries.sort();
+    let mut resolution = Resolution {
+        ids: HashSet::new(),
+        unmatched: Vec::new(),
+        ambiguous: Vec::new(),
+    };
+    for query in queries {
+        let mut matches = match_name(query, glyphs, arcs);
+        if matches.is_empty() {
+            for alternative in synonyms.alternatives(query) {
+                for glyph in match_name(alternative, glyphs, arcs) {
+                    if !matches.iter().any(|m| m.id() == glyph.id()) {
+                        matches.push(glyph);
+                    }
+                }
+            }
+        }
+        if matches.is_empty() {
+            resolution.unmatched.push(query.clone());
+            continue;
+        }
+        let entities: HashSet<(String, String)> =
+            matches.iter().filter_map(Match::entity).collect();
+        let ids: Vec<String> = matches.iter().map(|m| m.id().to_string()).collect();
+        if entities.len() > 1 {
+            resolution.ambiguous.push((query.clone(), ids.clone()));
+        }
+        resolution.ids.extend(ids);
+    }
+    resolution
+}
+
+enum Match<'a> {
+    /// An exact glyph or arc id, or the owner of an exactly matched port.
+    Id(&'a str),
+    /// A glyph found by label or cross-reference.
+    Glyph(&'a Glyph),
+}
+
+impl Match<'_> {
+    fn id(&self) -> &str {
+        match self {
+            Match::Id(id) => id,
+            Match::Glyph(glyph) => &glyph.id,
+        }
+    }
+
+    /// Clones and multimers of one entity share their base class and label.
+    fn entity(&self) -> Option<(String, String)> {
+        match self {
+            Match::Id(_) => None,
+            Match::Glyph(glyph) => Some((
+                glyph.class_name.trim_end_matches(" multimer").to_string(),
+                normalize(&glyph.label),
+            )),
+        }
+    }
+}
+
+fn match_name<'a>(name: &'a str, glyphs: &'a [Glyph], arcs: &'a [Arc]) -> Vec<Match<'a>> {
+    let name = name.trim();
+    // Highlights and fills apply to glyphs and arcs, so a port stands for its glyph.
+    let exact = glyphs
+        .iter()
+        .filter(|glyph| !glyph.id.is_empty())
+        .find(|glyph| glyph.id == name || glyph.ports.iter().any(|port| port.id == name))
+        .map(|glyph| glyph.id.as_str())
+        .or_else(|| {
+            arcs.iter()
+                .map(|arc| arc.id.as_str())
+                .find(|id| !id.is_empty() && *id == name)
+        });
+    if let Some(id) = exact {
+        return vec![Match::Id(id)];
+    }
+    let key = normalize(name);
+    if key.is_empty() {
+        return Vec::new();
+    }
+    glyphs
+        .iter()
+        .filter(|glyph| !glyph.id.is_empty() && !is_aux_class(&glyph.class_name))
+        .filter(|glyph| {
+            normalize(&glyph.label) == key
+                || glyph.xrefs.iter().any(|xref| xref_matches(xref, &key))
+        })
+        .map(Match::Glyph)
+        .collect()
+}
+
+/// Whether a normalized query names a cross-reference: its URI, its compact
+/// `namespace:id` form, or the bare id.
+fn xref_matches(xref: &Xref, key: &str) -> bool {
+    let uri = normalize(&xref.uri);
+    let url = normalize(&xref.url());
+    let compact = url.rsplit('/').next().unwrap_or(&url);
+    let bare = compact.split_once(':').map_or(compact, |(_, id)| id);
+    [uri.as_str(), url.as_str(), compact, bare].contains(&key)
+}
+
+fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BBox, Point, Port};
+
+    fn glyph(id: &str, class_name: &str, label: &str) -> Glyph {
+        let bbox = BBox {
+            x: 0.0,
+            y: 0.0,
+            w: 10.0,
+            h: 10.0,
+        };
+        Glyph::new(id, class_name, Some(bbox), label)
+    }
+
+    fn map() -> Vec<Glyph> {
+        let mut process = glyph("pr1", "process", "");
+        process.ports.push(Port {
+            id: "pr1.1".to_string(),
+            point: Point { x: 0.0, y: 5.0 },
+        });
+        let mut mapk = glyph("g1", "macromolecule", "MAPK1");
+        mapk.xrefs.push(Xref {
+            qualifier: "bqbiol:is".to_string(),
+            uri: "urn:miriam:uniprot:P28482".to_string(),
+        });
+        vec![
+            process,
+            mapk,
+            glyph("g2", "macromolecule", "MAPK1"),
+            glyph("g3", "simple chemical", "ATP"),
+            glyph("g4", "simple chemical", "Ca2+"),
+            glyph("g5", "macromolecule", "Ca2+"),
+        ]
+    }
+
+    fn ids(resolution: &Resolution) -> Vec<&str> {
+        let mut ids: Vec<&str> = resolution.ids.iter().map(String::as_str).collect();
+        ids.sort();
+        ids
+    }
+
+    fn queries(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn port_id_resolves_to_owning_glyph() {
+        let glyphs = map();
+        let resolution = resolve(&queries(&["pr1.1"]), &glyphs, &[], &Synonyms::none());
+        assert_eq!(ids(&resolution), ["pr1"]);
+        assert!(resolution.unmatched.is_empty());
+    }
+
+    #[test]
+    fn label_matches_ignore_case_and_whitespace() {
+        let glyphs = map();
+        let resolution = resolve(&queries(&[" a t p "]), &glyphs, &[], &Synonyms::none());
+        assert_eq!(ids(&resolution), ["g3"]);
+    }
+
+    #[test]
+    fn clones_of_one_entity_are_not_ambiguous() {
+        let glyphs = map();
+        let resolution = resolve(&queries(&["MAPK1"]), &glyphs, &[], &Synonyms::none());
+        assert_eq!(ids(&resolution), ["g1", "g2"]);
+        assert!(resolution.ambiguous.is_empty());
+    }
+
+    #[test]
+    fn label_shared_by_different_entities_is_ambiguous() {
+        let glyphs = map();
+        let resolution = resolve(&queries(&["Ca2+"]), &glyphs, &[], &Synonyms::none());
+        assert_eq!(ids(&resolution), ["g4", "g5"]);
+        assert_eq!(resolution.ambiguous.len(), 1);
+    }
+
+    #[test]
+    fn xref_matches_compact_and_bare_ids() {
+        let glyphs = map();
+        for query in ["uniprot:P28482", "P28482"] {
+            let resolution = resolve(&queries(&[query]), &glyphs, &[], &Synonyms::none());
+            assert_eq!(ids(&resolution), ["g1"], "{query}");
+        }
+    }
+
+    #[test]
+    fn synonyms_apply_only_without_a_direct_match() {
+        let glyphs = map();
+        let synonyms = Synonyms {
+            groups: vec![queries(&["ERK2", "MAPK1"]), queries(&["ATP", "Ca2+"])],
+        };
+        let resolution = resolve(&queries(&["erk2"]), &glyphs, &[], &synonyms);
+        assert_eq!(ids(&resolution), ["g1", "g2"]);
+        let resolution = resolve(&queries(&["ATP"]), &glyphs, &[], &synonyms);
+        assert_eq!(ids(&resolution), ["g3"]);
+    }
+
+    #[test]
+    fn unknown_query_is_unmatched() {
+        let glyphs = map();
+        let resolution = resolve(&queries(&["BRAF"]), &glyphs, &[], &Synonyms::none());
+        assert!(resolution.ids.is_empty());
+        assert_eq!(resolution.unmatched, ["BRAF"]);
+    }
+}
@@ -0,0 +1,524 @@
+//! Arc routing between glyphs and ports, and clipping of arc ends to glyph outlines.
+//!
+//! Candidate paths (straight, detours around blocking glyphs, or L/Z-shaped orthogonal
+//! paths) are scored by how many glyph bboxes they cross; the ends of the chosen path are
+//! then trimmed to the glyph outline traced by the same `path_*` functions the renderer uses.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use cairo::{Context as CairoContext, Format, ImageSurface};
+use clap::ValueEnum;
+
+use crate::layout::{
+    arc_anchor, bbox_center, endpoint_center, glyph_index, is_aux, is_container, is_process_like,
+};
+use crate::{path_glyph_outline, Arc, BBox, Glyph, PixelRect, Point};
+
+const OBSTACLE_MARGIN: f64 = 8.0;
+const CLIP_SAMPLES: usize = 64;
+const CLIP_BISECTIONS: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum RouteMode {
+    /// Keep arc paths as stored.
+    None,
+    /// Straight lines, bent around glyphs that would be crossed.
+    Straight,
+    /// Horizontal and vertical segments only.
+    Orthogonal,
+}
+
+/// Recompute every arc path from its source and target glyphs or ports.
+pub(crate) fn route_arcs(glyphs: &[Glyph], arcs: &mut [Arc], mode: RouteMode) -> Result<()> {
+    if mode == RouteMode::None {
+        return Ok(());
+    }
+    let index = glyph_index(glyphs);
+    let clipper = OutlineClipper::new()?;
+    for arc in arcs.iter_mut() {
+        let (Some(source), Some(target)) = (arc.source.clone(), arc.target.clone()) else {
+            continue;
+        };
+        let (Some(source_center), Some(target_center)) = (
+            endpoint_center(glyphs, &index, &source),
+            endpoint_center(glyphs, &index, &target),
+        ) else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (
+            route_anchor(glyphs, &index, &source, target_center, &arc.class_name),
+            route_anchor(glyphs, &index, &target, source_center, &arc.class_name),
+        ) else {
+            continue;
+        };
+        let obstacles = obstacles_for(glyphs, &index, &source, &target);
+        let mut points = match mode {
+            RouteMode::Straight => straight_route(start, end, &obstacles),
+            RouteMode::Orthogonal => orthogonal_route(start, end, &obstacles),
+            RouteMode::None => unreachable!(),
+        };
+        clipper.clip_arc_ends(glyphs, &index, &source, &target, &mut points)?;
+        arc.points = points;
+        arc.clear_glyph_positions();
+    }
+    Ok(())
+}
+
+/// Trim stored arc ends that stop inside or short of their source and target outlines.
+pub(crate) fn clip_arcs(glyphs: &[Glyph], arcs: &mut [Arc]) -> Result<()> {
+    let index = glyph_index(glyphs);
+    let clipper = OutlineClipper::new()?;
+    for arc in arcs.iter_mut() {
+        clipper.clip_arc_ends(
+            glyphs,
+            &index,
+            arc.source.as_deref().unwrap_or_default(),
+            arc.target.as_deref().unwrap_or_default(),
+            &mut arc.points,
+        )?;
+    }
+    Ok(())
+}
+
+/// Ports and process connectors are used as-is; other glyphs are entered at their center
+/// and trimmed to the outline afterwards.
+fn route_anchor(
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    id: &str,
+    toward: Point,
+    arc_class: &str,
+) -> Option<Point> {
+    let glyph = &glyphs[*index.get(id)?];
+    if glyph.id == id && !is_process_like(&glyph.class_name) {
+        return glyph.bbox.map(bbox_center);
+    }
+    arc_anchor(glyphs, index, id, toward, arc_class)
+}
+
+/// Bboxes an arc should not cross: every leaf glyph except its own ends and their contents.
+fn obstacles_for(
+    glyphs: &[Glyph],
+    index: &HashMap<String, usize>,
+    source: &str,
+    target: &str,
+) -> Vec<BBox> {
+    let ends: Vec<&str> = [source, target]
+        .iter()
+        .filter_map(|id| index.get(*id).map(|&idx| glyphs[idx].id.as_str()))
+        .collect();
+    let parents: HashMap<&str, &str> = glyphs
+        .iter()
+        .filter_map(|glyph| Some((glyph.id.as_str(), glyph.parent_id.as_deref()?)))
+        .collect();
+    let within_end = |glyph: &Glyph| {
+        let mut current = glyph.id.as_str();
+        for _ in 0..glyphs.len() {
+            if ends.contains(&current) {
+                return true;
+            }
+            match parents.get(current) {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+        false
+    };
+    glyphs
+        .iter()
+        .filter(|glyph| !is_aux(glyph) && !is_container(glyph) && !within_end(glyph))
+        .filter_map(|glyph| glyph.bbox)
+        .map(|bbox| BBox {
+            x: bbox.x - OBSTACLE_MARGIN,
+            y: bbox.y - OBSTACLE_MARGIN,
+            w: bbox.w + 2.0 * OBSTACLE_MARGIN,
+            h: bbox.h + 2.0 * OBSTACLE_MARGIN,
+        })
+        .collect()
+}
+
+fn straight_route(start: Point, end: Point, obstacles: &[BBox]) -> Vec<Point> {
+    let direct = vec![start, end];
+    let mut candidates = vec![direct.clone()];
+    for obstacle in obstacles
+        .iter()
+        .filter(|bbox| segment_hits_bbox(start, end, bbox))
+    {
+        for corner in bbox_corners(obstacle) {
+            candidates.push(vec![start, corner, end]);
+        }
+    }
+    best_candidate(candidates, obstacles).unwrap_or(direct)
+}
+
+fn orthogonal_route(start: Point, end: Point, obstacles: &[BBox]) -> Vec<Point> {
+    let mut channels_x = vec![(start.x + end.x) / 2.0];
+    let mut channels_y = vec![(start.y + end.y) / 2.0];
+    for bbox in obstacles {
+        channels_x.extend([bbox.x, bbox.x + bbox.w]);
+        channels_y.extend([bbox.y, bbox.y + bbox.h]);
+    }
+    let mut candidates = vec![
+        vec![
+            start,
+            Point {
+                x: end.x,
+                y: start.y,
+            },
+            end,
+        ],
+        vec![
+            start,
+            Point {
+                x: start.x,
+                y: end.y,
+            },
+            end,
+        ],
+    ];
+    for x in channels_x {
+        candidates.push(vec![
+            start,
+            Point { x, y: start.y },
+            Point { x, y: end.y },
+            end,
+        ]);
+    }
+    for y in channels_y {
+        candidates.push(vec![
+            start,
+            Point { x: start.x, y },
+            Point { x: end.x, y },
+            end,
+        ]);
+    }
+    best_candidate(candidates, obstacles).unwrap_or_else(|| vec![start, end])
+}
+
+/// Pick the path crossing the fewest obstacles, then the shortest, then the least bent.
+fn best_candidate(candidates: Vec<Vec<Point>>, obstacles: &[BBox]) -> Option<Vec<Point>> {
+    candidates
+        .into_iter()
+        .map(simplify_path)
+        .map(|path| {
+            let hits = path
+                .windows(2)
+                .map(|pair| {
+                    obstacles
+                        .iter()
+                        .filter(|bbox| segment_hits_bbox(pair[0], pair[1], bbox))
+                        .count()
+                })
+                .sum::<usize>();
+            (hits, path_length(&path), path.len(), path)
+        })
+        .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2)))
+        .map(|(_, _, _, path)| path)
+}
+
+/// Drop repeated and collinear interior points.
+fn simplify_path(points: Vec<Point>) -> Vec<Point> {
+    let mut result: Vec<Point> = Vec::with_capacity(points.len());
+    for point in points {
+        if let Some(last) = result.last() {
+            if (last.x - point.x).abs() < 1e-6 && (last.y - point.y).abs() < 1e-6 {
+                continue;
+            }
+        }
+        if result.len() >= 2 {
+            let a = result[result.len() - 2];
+            let b = result[result.len() - 1];
+            let cross = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+            if cross.abs() < 1e-6 {
+                result.pop();
+            }
+        }
+        result.push(point);
+    }
+    result
+}
+
+fn path_length(points: &[Point]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y))
+        .sum()
+}
+
+fn bbox_corners(bbox: &BBox) -> [Point; 4] {
+    [
+        Point {
+            x: bbox.x,
+            y: bbox.y,
+        },
+        Point {
+            x: bbox.x + bbox.w,
+            y: bbox.y,
+        },
+        Point {
+            x: bbox.x + bbox.w,
+            y: bbox.y + bbox.h,
+        },
+        Point {
+            x: bbox.x,
+            y: bbox.y + bbox.h,
+        },
+    ]
+}
+
+/// Liang-Barsky test for a segment passing through the interior of a bbox.
+fn segment_hits_bbox(a: Point, b: Point, bbox: &BBox) -> bool {
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    let checks = [
+        (-dx, a.x - bbox.x),
+        (dx, bbox.x + bbox.w - a.x),
+        (-dy, a.y - bbox.y),
+        (dy, bbox.y + bbox.h - a.y),
+    ];
+    for (p, q) in checks {
+        if p == 0.0 {
+            if q <= 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 >= t1 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Hit-tests points against glyph outlines on a scratch Cairo context in data coordinates.
+pub(crate) struct OutlineClipper {
+    ctx: CairoContext,
+}
+
+impl OutlineClipper {
+    pub(crate) fn new() -> Result<Self> {
+        let surface =
+            ImageSurface::create(Format::A8, 1, 1).context("Failed to create clip surface")?;
+        let ctx = CairoContext::new(&surface).context("Failed to create clip context")?;
+        Ok(Self { ctx })
+    }
+
+    /// Trim the first and last segment of an arc to the outlines of its source and target.
+    pub(crate) fn clip_arc_ends(
+        &self,
+        glyphs: &[Glyph],
+        index: &HashMap<String, usize>,
+        source: &str,
+        target: &str,
+        points: &mut [Point],
+    ) -> Result<()> {
+        let count = points.len();
+        if count < 2 {
+            return Ok(());
+        }
+        if let Some(glyph) = clip_glyph(glyphs, index, source) {
+            points[0] = self.clip_to_outline(glyph, points[1], points[0])?;
+        }
+        if let Some(glyph) = clip_glyph(glyphs, index, target) {
+            points[count - 1] =
+                self.clip_to_outline(glyph, points[count - 2], points[count - 1])?;
+        }
+        Ok(())
+    }
+
+    /// Move `end` to where the ray from `from` through `end` first enters the glyph outline.
+    ///
+    /// Ends that lie outside the glyph bbox (ports, process connectors) are left alone.
+    pub(crate) fn clip_to_outline(&self, glyph: &Glyph, from: Point, end: Point) -> Result<Point> {
+        let Some(bbox) = glyph.bbox else {
+            return Ok(end);
+        };
+        let slack = 1.0;
+        let inside_bbox = end.x >= bbox.x - slack
+            && end.x <= bbox.x + bbox.w + slack
+            && end.y >= bbox.y - slack
+            && end.y <= bbox.y + bbox.h + slack;
+        let dx = end.x - from.x;
+        let dy = end.y - from.y;
+        let length = dx.hypot(dy);
+        if !inside_bbox || length == 0.0 {
+            return Ok(end);
+        }
+        let rect = PixelRect {
+            x0: bbox.x,
+            y0: bbox.y,
+            width: bbox.w,
+            height: bbox.h,
+            center: bbox_center(bbox),
+        };
+        path_glyph_outline(
+            &self.ctx,
+            rect,
+            &glyph.class_name,
+            glyph.orientation.as_deref(),
+        )?;
+        if self.ctx.in_fill(from.x, from.y)? {
+            self.ctx.new_path();
+            return Ok(end);
+        }
+        // Extend past the end so arcs stopping short of a rounded outline still reach it.
+        let reach = length + bbox.w.hypot(bbox.h);
+        let far = Point {
+            x: from.x + dx / length * reach,
+            y: from.y + dy / length * reach,
+        };
+        let at = |t: f64| Point {
+            x: from.x + (far.x - from.x) * t,
+            y: from.y + (far.y - from.y) * t,
+        };
+        let mut outside = 0.0;
+        let mut inside = None;
+        for step in 1..=CLIP_SAMPLES {
+            let t = step as f64 / CLIP_SAMPLES as f64;
+            let point = at(t);
+            if self.ctx.in_fill(point.x, point.y)? {
+                inside = Some(t);
+                break;
+            }
+            outside = t;
+        }
+        let Some(mut inside) = inside else {
+            self.ctx.new_path();
+            return Ok(end);
+        };
+        for _ in 0..CLIP_BISECTIONS {
+            let mid = (outside + inside) / 2.0;
+            let point = at(mid);
+            if self.ctx.in_fill(point.x, point.y)? {
+                inside = mid;
+            } else {
+                outside = mid;
+            }
+        }
+        self.ctx.new_path();
+        Ok(at(outside))
+    }
+}
+
+/// Glyph whose outline an arc end should be clipped to; port ends are never clipped.
+fn clip_glyph<'a>(
+    glyphs: &'a [Glyph],
+    index: &HashMap<String, usize>,
+    id: &str,
+) -> Option<&'a Glyph> {
+    let glyph = &glyphs[*index.get(id)?];
+    (glyph.id == id).then_some(glyph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn coords(points: &[Point]) -> Vec<(f64, f64)> {
+        points.iter().map(|point| (point.x, point.y)).collect()
+    }
+
+    fn hits(path: &[Point], obstacles: &[BBox]) -> usize {
+        path.windows(2)
+            .map(|pair| {
+                obstacles
+                    .iter()
+                    .filter(|bbox| segment_hits_bbox(pair[0], pair[1], bbox))
+                    .count()
+            })
+            .sum()
+    }
+
+    const BLOCK: BBox = BBox {
+        x: 40.0,
+        y: -10.0,
+        w: 20.0,
+        h: 30.0,
+    };
+
+    #[test]
+    fn segments_hit_the_interior_but_not_the_edge() {
+        assert!(segment_hits_bbox(
+            point(0.0, 0.0),
+            point(100.0, 0.0),
+            &BLOCK
+        ));
+        assert!(!segment_hits_bbox(
+            point(0.0, -10.0),
+            point(100.0, -10.0),
+            &BLOCK
+        ));
+        assert!(!segment_hits_bbox(
+            point(0.0, 0.0),
+            point(30.0, 0.0),
+            &BLOCK
+        ));
+    }
+
+    #[test]
+    fn straight_route_stays_direct_when_nothing_is_in_the_way() {
+        let path = straight_route(point(0.0, 0.0), point(100.0, 0.0), &[]);
+        assert_eq!(coords(&path), [(0.0, 0.0), (100.0, 0.0)]);
+    }
+
+    #[test]
+    fn straight_route_bends_at_a_corner_that_clears_the_obstacle() {
+        let block = BBox {
+            x: 40.0,
+            y: -60.0,
+            w: 20.0,
+            h: 20.0,
+        };
+        let path = straight_route(point(0.0, 0.0), point(100.0, -100.0), &[block]);
+        assert_eq!(hits(&path, &[block]), 0);
+        let corner = (path[1].x, path[1].y);
+        assert_eq!(path.len(), 3);
+        assert!(corner == (60.0, -40.0) || corner == (40.0, -60.0));
+    }
+
+    #[test]
+    fn orthogonal_route_uses_axis_aligned_segments_around_obstacles() {
+        let start = point(0.0, 0.0);
+        let end = point(100.0, 50.0);
+        let path = orthogonal_route(start, end, &[BLOCK]);
+        assert_eq!(hits(&path, &[BLOCK]), 0);
+        assert!(path
+            .windows(2)
+            .all(|pair| pair[0].x == pair[1].x || pair[0].y == pair[1].y));
+        assert_eq!(coords(&path[..1]), [(0.0, 0.0)]);
+        assert_eq!(coords(&path[path.len() - 1..]), [(100.0, 50.0)]);
+    }
+
+    #[test]
+    fn best_candidate_prefers_fewer_hits_over_length() {
+        let through = vec![point(0.0, 0.0), point(100.0, 0.0)];
+        let around = vec![point(0.0, 0.0), point(50.0, -40.0), point(100.0, 0.0)];
+        let best = best_candidate(vec![through, around], &[BLOCK]).unwrap();
+        assert_eq!(best.len(), 3);
+    }
+
+    #[test]
+    fn simplify_drops_repeated_and_collinear_points() {
+        let path = simplify_path(vec![
+            point(0.0, 0.0),
+            point(0.0, 0.0),
+            point(50.0, 0.0),
+            point(100.0, 0.0),
+            point(100.0, 50.0),
+        ]);
+        assert_eq!(coords(&path), [(0.0, 0.0), (100.0, 0.0), (100.0, 50.0)]);
+    }
+}
@@ -0,0 +1,745 @@
+//! SBML import: the model's species, reactions and compartments as SBGN PD glyphs and arcs,
+//! placed by the Layout package when present and filled by its Render styles.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use roxmltree::{Document, Node};
+
+use crate::annotations::{notes_text, xrefs, Xref};
+use crate::layout::{bbox_center, cubic_bezier, distance, BEZIER_SAMPLES};
+use crate::{Arc, BBox, Glyph, Point};
+
+const PROCESS_SIZE: f64 = 24.0;
+
+/// Whether the document is an SBML model rather than SBGN-ML.
+pub(crate) fn is_sbml(doc: &Document) -> bool {
+    doc.root_element().has_tag_name("sbml")
+}
+
+/// Convert an SBML model to SBGN PD glyphs and arcs.
+///
+/// Species, reactions and compartments are classed from their SBO terms. When the model
+/// carries a Layout package layout (L3 package or L2 annotation), the first layout provides
+/// bboxes and curves; otherwise every element is emitted without geometry for auto-layout.
+/// Render package styles of that layout give species glyphs their fill; other Render
+/// properties (strokes, fonts, shapes) are not applied.
+pub(crate) fn parse_sbml(doc: &Document) -> Result<(Vec<Glyph>, Vec<Arc>)> {
+    let model = doc
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("model"))
+        .ok_or_else(|| anyhow!("No <model> element found in SBML"))?;
+    let model = Model::parse(&model);
+    let layout = model_node_layout(doc);
+    match layout {
+        Some(layout) => Ok(from_layout(&model, &layout, &render_styles(doc, &layout))),
+        None => Ok(from_model(&model)),
+    }
+}
+
+struct Species {
+    name: String,
+    compartment: Option<String>,
+    class_name: &'static str,
+    notes: Option<String>,
+    xrefs: Vec<Xref>,
+}
+
+struct SpeciesRef {
+    id: Option<String>,
+    species: String,
+    stoichiometry: Option<String>,
+    class_name: &'static str,
+}
+
+struct Reaction {
+    id: String,
+    class_name: &'static str,
+    refs: Vec<SpeciesRef>,
+}
+
+struct Model {
+    compartments: Vec<(String, String)>,
+    species: HashMap<String, Species>,
+    species_order: Vec<String>,
+    reactions: Vec<Reaction>,
+}
+
+impl Model {
+    fn parse(model: &Node) -> Self {
+        let compartments = list_items(model, "listOfCompartments", "compartment")
+            .filter_map(|node| {
+                let id = attr(&node, "id")?.to_string();
+                let name = attr(&node, "name").unwrap_or(&id).to_string();
+                Some((id, name))
+            })
+            .collect();
+
+        let mut species = HashMap::new();
+        let mut species_order = Vec::new();
+        for node in list_items(model, "listOfSpecies", "species") {
+            let Some(id) = attr(&node, "id") else {
+                continue;
+            };
+            species_order.push(id.to_string());
+            species.insert(
+                id.to_string(),
+                Species {
+                    name: attr(&node, "name").unwrap_or(id).to_string(),
+                    compartment: attr(&node, "compartment").map(str::to_string),
+                    class_name: species_class(sbo_term(&node)),
+                    notes: notes_text(&node),
+                    xrefs: xrefs(&node),
+                },
+            );
+        }
+
+        let reactions = list_items(model, "listOfReactions", "reaction")
+            .filter_map(|node| {
+                let id = attr(&node, "id")?.to_string();
+                let reversible = attr(&node, "reversible") == Some("true");
+                let consumption = if reversible {
+                    "production"
+                } else {
+                    "consumption"
+                };
+                let mut refs = Vec::new();
+                for (list, item, class_name) in [
+                    ("listOfReactants", "speciesReference", consumption),
+                    ("listOfProducts", "speciesReference", "production"),
+                    ("listOfModifiers", "modifierSpeciesReference", "modulation"),
+                ] {
+                    for item in list_items(&node, list, item) {
+                        let Some(species) = attr(&item, "species") else {
+                            continue;
+                        };
+                        let class_name = if class_name == "modulation" {
+                            modifier_class(sbo_term(&item))
+                        } else {
+                            class_name
+                        };
+                        refs.push(SpeciesRef {
+                            id: attr(&item, "id").map(str::to_string),
+                            species: species.to_string(),
+                            stoichiometry: attr(&item, "stoichiometry")
+                                .filter(|value| value.parse::<f64>().is_ok_and(|v| v != 1.0))
+                                .map(str::to_string),
+                            class_name,
+                        });
+                    }
+                }
+                Some(Reaction {
+                    id,
+                    class_name: reaction_class(sbo_term(&node)),
+                    refs,
+                })
+            })
+            .collect();
+
+        Self {
+            compartments,
+            species,
+            species_order,
+            reactions,
+        }
+    }
+
+    fn species_label(&self, id: &str) -> String {
+        self.species
+            .get(id)
+            .map(|species| species.name.clone())
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// Copy a species' notes and cross-references onto a glyph drawn for it.
+    fn annotate(&self, glyph: &mut Glyph, id: &str) {
+        if let Some(species) = self.species.get(id) {
+            glyph.notes = species.notes.clone();
+            glyph.xrefs = species.xrefs.clone();
+        }
+    }
+
+    fn species_class(&self, id: &str) -> &'static str {
+        self.species
+            .get(id)
+            .map(|species| species.class_name)
+            .unwrap_or("unspecified entity")
+    }
+}
+
+/// Build glyphs and arcs without geometry, one glyph per compartment, species and reaction.
+fn from_model(model: &Model) -> (Vec<Glyph>, Vec<Arc>) {
+    let mut glyphs = Vec::new();
+    for (id, name) in &model.compartments {
+        glyphs.push(Glyph::new(id, "compartment", None, name));
+    }
+    for id in &model.species_order {
+        let mut species = Glyph::new(id, model.species_class(id), None, &model.species_label(id));
+        species.compartment_ref = model.species[id]
+            .compartment
+            .clone()
+            .filter(|compartment| model.compartments.iter().any(|(id, _)| id == compartment));
+        model.annotate(&mut species, id);
+        glyphs.push(species);
+    }
+    let mut arcs = Vec::new();
+    for reaction in &model.reactions {
+        glyphs.push(Glyph::new(&reaction.id, reaction.class_name, None, ""));
+        for (index, species_ref) in reaction.refs.iter().enumerate() {
+            let id = species_ref
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("{}_{}", reaction.id, index));
+            arcs.push(reference_arc(
+                id,
+                species_ref,
+                &species_ref.species,
+                &reaction.id,
+                Vec::new(),
+            ));
+        }
+    }
+    (glyphs, arcs)
+}
+
+/// Build glyphs and arcs from a Layout package `<layout>` element.
+fn from_layout(model: &Model, layout: &Node, styles: &[FillStyle]) -> (Vec<Glyph>, Vec<Arc>) {
+    let texts: HashMap<String, String> = list_items(layout, "listOfTextGlyphs", "textGlyph")
+        .filter_map(|node| {
+            let target = attr(&node, "graphicalObject")?.to_string();
+            let text = attr(&node, "text")
+                .map(str::to_string)
+                .or_else(|| attr(&node, "originOfText").map(|id| model.species_label(id)))?;
+            Some((target, text))
+        })
+        .collect();
+
+    let mut glyphs = Vec::new();
+    let mut compartment_glyphs: HashMap<String, String> = HashMap::new();
+    for node in list_items(layout, "listOfCompartmentGlyphs", "compartmentGlyph") {
+        let Some(id) = attr(&node, "id") else {
+            continue;
+        };
+        let compartment = attr(&node, "compartment").unwrap_or_default();
+        let Some(name) = model
+            .compartments
+            .iter()
+            .find(|(compartment_id, _)| compartment_id == compartment)
+            .map(|(_, name)| name.clone())
+        else {
+            continue;
+        };
+        compartment_glyphs
+            .entry(compartment.to_string())
+            .or_insert_with(|| id.to_string());
+        let label = texts.get(id).cloned().unwrap_or(name);
+        glyphs.push(Glyph::new(id, "compartment", bounding_box(&node), &label));
+    }
+
+    let mut species_glyphs: HashMap<String, String> = HashMap::new();
+    let mut glyph_counts: HashMap<String, usize> = HashMap::new();
+    let species_nodes: Vec<Node> =
+        list_items(layout, "listOfSpeciesGlyphs", "speciesGlyph").collect();
+    for node in &species_nodes {
+        if let Some(species) = attr(node, "species") {
+            *glyph_counts.entry(species.to_string()).or_default() += 1;
+        }
+    }
+    for node in &species_nodes {
+        let (Some(id), Some(species)) = (attr(node, "id"), attr(node, "species")) else {
+            continue;
+        };
+        species_glyphs.insert(id.to_string(), species.to_string());
+        let label = texts
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| model.species_label(species));
+        let mut entry = Glyph::new(id, model.species_class(species), bounding_box(node), &label);
+        // Several glyphs for one species are SBGN clones.
+        entry.has_clone = glyph_counts.get(species).copied().unwrap_or(0) > 1
+            && entry.class_name != "source and sink";
+        entry.compartment_ref = model
+            .species
+            .get(species)
+            .and_then(|species| species.compartment.as_deref())
+            .and_then(|compartment| compartment_glyphs.get(compartment))
+            .cloned();
+        entry.fill = style_fill(styles, id, "SPECIESGLYPH");
+        model.annotate(&mut entry, species);
+        glyphs.push(entry);
+    }
+
+    let reactions: HashMap<&str, &Reaction> = model
+        .reactions
+        .iter()
+        .map(|reaction| (reaction.id.as_str(), reaction))
+        .collect();
+    let mut arcs = Vec::new();
+    for node in list_items(layout, "listOfReactionGlyphs", "reactionGlyph") {
+        let Some(id) = attr(&node, "id") else {
+            continue;
+        };
+        let reaction = attr(&node, "reaction").and_then(|id| reactions.get(id).copied());
+        let class_name = reaction
+            .map(|reaction| reaction.class_name)
+            .unwrap_or("process");
+        let backbone = curve_points(&node);
+        let bbox = bounding_box(&node)
+            .filter(|bbox| bbox.w > 0.0 && bbox.h > 0.0)
+            .or_else(|| polyline_midpoint(&backbone).map(process_bbox));
+        glyphs.push(Glyph::new(id, class_name, bbox, ""));
+
+        for (index, ref_node) in list_items(
+            &node,
+            "listOfSpeciesReferenceGlyphs",
+            "speciesReferenceGlyph",
+        )
+        .enumerate()
+        {
+            let Some(species_glyph) = attr(&ref_node, "speciesGlyph") else {
+                continue;
+            };
+            let species = species_glyphs.get(species_glyph);
+            let species_ref = reaction.and_then(|reaction| {
+                let by_ref = attr(&ref_node, "speciesReference");
+                reaction
+                    .refs
+                    .iter()
+                    .find(|item| by_ref.is_some() && item.id.as_deref() == by_ref)
+                    .or_else(|| {
+                        let species = species?;
+                        reaction.refs.iter().find(|item| &item.species == species)
+                    })
+            });
+            let class_name = attr(&ref_node, "role")
+                .and_then(role_class)
+                .or(species_ref.map(|item| item.class_name))
+                .unwrap_or("consumption");
+            let class_name = match (class_name, species_ref) {
+                // A role of "modifier" is refined by the modifier's SBO term.
+                ("modulation", Some(item)) => item.class_name,
+                _ => class_name,
+            };
+            let arc_id = attr(&ref_node, "id")
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{id}_{index}"));
+            let template = SpeciesRef {
+                id: None,
+                species: species_glyph.to_string(),
+                stoichiometry: species_ref.and_then(|item| item.stoichiometry.clone()),
+                class_name,
+            };
+            let species_bbox = glyphs
+                .iter()
+                .find(|glyph| glyph.id == species_glyph)
+                .and_then(|glyph| glyph.bbox);
+            let points = orient_curve(curve_points(&ref_node), species_bbox, bbox, class_name);
+            arcs.push(reference_arc(arc_id, &template, species_glyph, id, points));
+        }
+    }
+    (glyphs, arcs)
+}
+
+/// Arc between a species glyph and a process, directed by its SBGN class.
+fn reference_arc(
+    id: String,
+    species_ref: &SpeciesRef,
+    species: &str,
+    process: &str,
+    points: Vec<Point>,
+) -> Arc {
+    let (source, target) = if species_ref.class_name == "production" {
+        (process, species)
+    } else {
+        (species, process)
+    };
+    let glyphs = species_ref
+        .stoichiometry
+        .iter()
+        .map(|value| Glyph::new(&format!("{id}_stoichiometry"), "stoichiometry", None, value))
+        .collect();
+    Arc {
+        id,
+        class_name: species_ref.class_name.to_string(),
+        source: Some(source.to_string()),
+        target: Some(target.to_string()),
+        points,
+        glyphs,
+        ports: Vec::new(),
+        extras: Vec::new(),
+    }
+}
+
+/// Order curve points from source to target and extend them to the process center.
+fn orient_curve(
+    mut points: Vec<Point>,
+    species: Option<BBox>,
+    process: Option<BBox>,
+    class_name: &str,
+) -> Vec<Point> {
+    let (Some(first), Some(last)) = (points.first().copied(), points.last().copied()) else {
+        return points;
+    };
+    // Layout curves usually run from the reaction to the species; put the species end first.
+    if let Some(species) = species {
+        let center = bbox_center(species);
+        if distance(first, center) > distance(last, center) {
+            points.reverse();
+        }
+    } else if let Some(process) = process {
+        if distance(first, bbox_center(process)) < distance(last, bbox_center(process)) {
+            points.reverse();
+        }
+    }
+    // Curves often stop at the reaction backbone's end rather than the process node.
+    if let Some(process) = process {
+        let center = bbox_center(process);
+        if points
+            .last()
+            .is_some_and(|end| !bbox_contains(process, *end))
+        {
+            points.push(center);
+        }
+    }
+    if class_name == "production" {
+        points.reverse();
+    }
+    points
+}
+
+/// SBGN PD entity pool class for a species SBO term.
+fn species_class(sbo: Option<u32>) -> &'static str {
+    match sbo {
+        Some(247 | 327 | 328) => "simple chemical",
+        Some(245 | 246 | 252 | 248 | 249) => "macromolecule",
+        Some(250 | 251 | 354 | 634) => "nucleic acid feature",
+        Some(253 | 296 | 297) => "complex",
+        Some(291) => "source and sink",
+        Some(405) => "perturbing agent",
+        Some(358) => "phenotype",
+        _ => "unspecified entity",
+    }
+}
+
+/// SBGN PD process class for a reaction SBO term.
+fn reaction_class(sbo: Option<u32>) -> &'static str {
+    match sbo {
+        Some(177) => "association",
+        Some(180) => "dissociation",
+        Some(396) => "uncertain process",
+        Some(397) => "omitted process",
+        _ => "process",
+    }
+}
+
+/// SBGN PD modulation arc class for a modifier SBO term.
+fn modifier_class(sbo: Option<u32>) -> &'static str {
+    match sbo {
+        Some(13 | 460) => "catalysis",
+        Some(459 | 21 | 462) => "stimulation",
+        Some(461) => "necessary stimulation",
+        Some(20 | 206 | 207 | 537) => "inhibition",
+        _ => "modulation",
+    }
+}
+
+/// SBGN arc class for a Layout package `role` attribute.
+fn role_class(role: &str) -> Option<&'static str> {
+    match role {
+        "substrate" | "sidesubstrate" => Some("consumption"),
+        "product" | "sideproduct" => Some("production"),
+        "activator" => Some("stimulation"),
+        "inhibitor" => Some("inhibition"),
+        "modifier" => Some("modulation"),
+        _ => None,
+    }
+}
+
+/// The first `<layout>` in the document, from the L3 package or an L2 annotation.
+fn model_node_layout<'a, 'input>(doc: &'a Document<'input>) -> Option<Node<'a, 'input>> {
+    doc.descendants().find(|node| {
+        node.has_tag_name("layout")
+            && node
+                .parent_element()
+                .is_some_and(|parent| parent.has_tag_name("listOfLayouts"))
+    })
+}
+
+/// A Render package style reduced to the fill it gives the glyphs it selects.
+struct FillStyle {
+    ids: Vec<String>,
+    types: Vec<String>,
+    fill: (f64, f64, f64),
+}
+
+/// Fill styles for `layout`, its local render information first so it wins over global.
+fn render_styles(doc: &Document, layout: &Node) -> Vec<FillStyle> {
+    let infos = layout
+        .children()
+        .filter(|node| node.has_tag_name("listOfRenderInformation"))
+        .chain(
+            doc.descendants()
+                .filter(|node| node.has_tag_name("listOfGlobalRenderInformation")),
+        )
+        .flat_map(|list| list.children())
+        .filter(|node| node.has_tag_name("renderInformation"));
+    let mut styles = Vec::new();
+    for info in infos {
+        let colors: HashMap<&str, &str> =
+            list_items(&info, "listOfColorDefinitions", "colorDefinition")
+                .filter_map(|node| Some((attr(&node, "id")?, attr(&node, "value")?)))
+                .collect();
+        let style_nodes = info
+            .children()
+            .filter(|node| node.has_tag_name("listOfStyles"))
+            .flat_map(|list| list.children())
+            .filter(|node| node.has_tag_name("style") || node.has_tag_name("localStyle"));
+        for style in style_nodes {
+            // Gradient ids and "none" do not resolve to a color and leave the theme fill.
+            let Some(fill) = style
+                .children()
+                .find(|node| node.has_tag_name("g"))
+                .and_then(|group| attr(&group, "fill"))
+                .map(|fill| colors.get(fill).copied().unwrap_or(fill))
+                .and_then(render_color)
+            else {
+                continue;
+            };
+            let list = |name: &str| {
+                attr(&style, name)
+                    .map(|value| value.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default()
+            };
+            styles.push(FillStyle {
+                ids: list("idList"),
+                types: list("typeList"),
+                fill,
+            });
+        }
+    }
+    styles
+}
+
+/// Fill for a layout glyph; a style naming its id wins over one naming its type.
+fn style_fill(styles: &[FillStyle], id: &str, glyph_type: &str) -> Option<(f64, f64, f64)> {
+    styles
+        .iter()
+        .find(|style| style.ids.iter().any(|item| item == id))
+        .or_else(|| {
+            styles.iter().find(|style| {
+                style
+                    .types
+                    .iter()
+                    .any(|item| item == glyph_type || item == "ANY")
+            })
+        })
+        .map(|style| style.fill)
+}
+
+/// A Render `#rrggbb` or `#rrggbbaa` color; the alpha channel is dropped.
+fn render_color(value: &str) -> Option<(f64, f64, f64)> {
+    let value = value.trim();
+    if value.len() != 7 && value.len() != 9 {
+        return None;
+    }
+    crate::parse_hex_color(value.get(..7)?).ok()
+}
+
+/// Elements named `item` inside the `list` child of `node`.
+pub(crate) fn list_items<'a, 'input: 'a>(
+    node: &Node<'a, 'input>,
+    list: &'a str,
+    item: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(list))
+        .flat_map(|child| child.children())
+        .filter(move |child| child.has_tag_name(item))
+}
+
+/// Attribute lookup by local name, ignoring the `layout:` or other namespace prefix.
+pub(crate) fn attr<'a>(node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .find(|attribute| attribute.name() == name)
+        .map(|attribute| attribute.value())
+}
+
+fn sbo_term(node: &Node) -> Option<u32> {
+    let term = attr(node, "sboTerm")?;
+    term.trim_start_matches("SBO:").parse().ok()
+}
+
+fn bounding_box(node: &Node) -> Option<BBox> {
+    let bbox = node
+        .children()
+        .find(|child| child.has_tag_name("boundingBox"))?;
+    let position = bbox
+        .children()
+        .find(|child| child.has_tag_name("position"))?;
+    let dimensions = bbox
+        .children()
+        .find(|child| child.has_tag_name("dimensions"))?;
+    Some(BBox {
+        x: number(&position, "x")?,
+        y: number(&position, "y")?,
+        w: number(&dimensions, "width")?,
+        h: number(&dimensions, "height")?,
+    })
+}
+
+/// Flatten a `<curve>` child into a polyline, sampling cubic Béziers.
+fn curve_points(node: &Node) -> Vec<Point> {
+    let mut points: Vec<Point> = Vec::new();
+    let segments = node
+        .children()
+        .filter(|child| child.has_tag_name("curve"))
+        .flat_map(|curve| curve.children())
+        .filter(|child| child.has_tag_name("listOfCurveSegments"))
+        .flat_map(|list| list.children())
+        .filter(|child| child.has_tag_name("curveSegment"));
+    for segment in segments {
+        let point = |name: &str| {
+            let node = segment.children().find(|child| child.has_tag_name(name))?;
+            Some(Point {
+                x: number(&node, "x")?,
+                y: number(&node, "y")?,
+            })
+        };
+        let (Some(start), Some(end)) = (point("start"), point("end")) else {
+            continue;
+        };
+        let is_bezier = attr(&segment, "type").is_some_and(|kind| kind.ends_with("CubicBezier"));
+        let mut segment_points = vec![start];
+        if let (true, Some(c1), Some(c2)) = (is_bezier, point("basePoint1"), point("basePoint2")) {
+            for step in 1..BEZIER_SAMPLES {
+                segment_points.push(cubic_bezier(
+                    start,
+                    c1,
+                    c2,
+                    end,
+                    step as f64 / BEZIER_SAMPLES as f64,
+                ));
+            }
+        }
+        segment_points.push(end);
+        for point in segment_points {
+            if points
+                .last()
+                .is_some_and(|last| distance(*last, point) < 1e-6)
+            {
+                continue;
+            }
+            points.push(point);
+        }
+    }
+    points
+}
+
+/// Point halfway along a polyline by length.
+fn polyline_midpoint(points: &[Point]) -> Option<Point> {
+    let total: f64 = points
+        .windows(2)
+        .map(|pair| distance(pair[0], pair[1]))
+        .sum();
+    let mut remaining = total / 2.0;
+    for pair in points.windows(2) {
+        let length = distance(pair[0], pair[1]);
+        if length >= remaining && length > 0.0 {
+            let t = remaining / length;
+            return Some(Point {
+                x: pair[0].x + (pair[1].x - pair[0].x) * t,
+                y: pair[0].y + (pair[1].y - pair[0].y) * t,
+            });
+        }
+        remaining -= length;
+    }
+    points.first().copied()
+}
+
+fn process_bbox(center: Point) -> BBox {
+    BBox {
+        x: center.x - PROCESS_SIZE / 2.0,
+        y: center.y - PROCESS_SIZE / 2.0,
+        w: PROCESS_SIZE,
+        h: PROCESS_SIZE,
+    }
+}
+
+fn number(node: &Node, name: &str) -> Option<f64> {
+    attr(node, name)?.parse().ok()
+}
+
+fn bbox_contains(bbox: BBox, point: Point) -> bool {
+    point.x >= bbox.x
+        && point.x <= bbox.x + bbox.w
+        && point.y >= bbox.y
+        && point.y <= bbox.y + bbox.h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENDERED: &str = r##"<sbml xmlns="http://www.sbml.org/sbml/level3/version1/core" xmlns:layout="http://www.sbml.org/sbml/level3/version1/layout/version1" xmlns:render="http://www.sbml.org/sbml/level3/version1/render/version1" level="3" version="1">
+  <model id="m">
+    <listOfCompartments><compartment id="c" name="cell"/></listOfCompartments>
+    <listOfSpecies>
+      <species id="a" name="A" compartment="c" sboTerm="SBO:0000245"/>
+      <species id="b" name="B" compartment="c" sboTerm="SBO:0000247"/>
+    </listOfSpecies>
+    <listOfReactions>
+      <reaction id="r" reversible="false">
+        <listOfReactants><speciesReference species="a"/></listOfReactants>
+        <listOfProducts><speciesReference species="b"/></listOfProducts>
+      </reaction>
+    </listOfReactions>
+    <layout:listOfLayouts>
+      <layout:layout layout:id="l">
+        <layout:dimensions layout:width="300" layout:height="100"/>
+        <layout:listOfSpeciesGlyphs>
+          <layout:speciesGlyph layout:id="ga" layout:species="a">
+            <layout:boundingBox><layout:position layout:x="10" layout:y="40"/><layout:dimensions layout:width="60" layout:height="30"/></layout:boundingBox>
+          </layout:speciesGlyph>
+          <layout:speciesGlyph layout:id="gb" layout:species="b">
+            <layout:boundingBox><layout:position layout:x="200" layout:y="40"/><layout:dimensions layout:width="60" layout:height="30"/></layout:boundingBox>
+          </layout:speciesGlyph>
+        </layout:listOfSpeciesGlyphs>
+        <render:listOfRenderInformation>
+          <render:renderInformation render:id="local">
+            <render:listOfColorDefinitions>
+              <render:colorDefinition render:id="red" render:value="#ff0000ff"/>
+            </render:listOfColorDefinitions>
+            <render:listOfStyles>
+              <render:style render:idList="ga"><render:g render:fill="red"/></render:style>
+            </render:listOfStyles>
+          </render:renderInformation>
+        </render:listOfRenderInformation>
+      </layout:layout>
+      <render:listOfGlobalRenderInformation>
+        <render:renderInformation render:id="global">
+          <render:listOfStyles>
+            <render:style render:typeList="SPECIESGLYPH"><render:g render:fill="#00ff00"/></render:style>
+          </render:listOfStyles>
+        </render:renderInformation>
+      </render:listOfGlobalRenderInformation>
+    </layout:listOfLayouts>
+  </model>
+</sbml>"##;
+
+    #[test]
+    fn render_fills_prefer_local_id_styles() {
+        let doc = Document::parse(RENDERED).unwrap();
+        let (glyphs, _) = parse_sbml(&doc).unwrap();
+        let fill = |id: &str| glyphs.iter().find(|glyph| glyph.id == id).unwrap().fill;
+        assert_eq!(fill("ga"), Some((1.0, 0.0, 0.0)));
+        assert_eq!(fill("gb"), Some((0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn render_color_accepts_alpha_and_rejects_gradients() {
+        assert_eq!(render_color("#0000ff80"), Some((0.0, 0.0, 1.0)));
+        assert_eq!(render_color("linearGradient_1"), None);
+        assert_eq!(render_color("none"), None);
+    }
+}
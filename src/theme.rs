@@ -0,0 +1,213 @@
+//! Canvas and glyph colors chosen once from the command line and read by the drawing functions.
+
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::parse_hex_color;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Okabe-Ito sky blue, yellow and bluish green, mixed into the fills in colorblind mode.
+const COLORBLIND_ENTITY_TINT: (f64, f64, f64) = (0.337, 0.706, 0.914);
+const COLORBLIND_COMPLEX_TINT: (f64, f64, f64) = (0.941, 0.894, 0.259);
+const COLORBLIND_COMPARTMENT_TINT: (f64, f64, f64) = (0.0, 0.620, 0.451);
+/// Share of the tint in a colorblind fill, light enough to keep labels readable.
+const COLORBLIND_TINT_AMOUNT: f64 = 0.3;
+
+const fn gray(level: u8) -> (f64, f64, f64) {
+    let value = level as f64 / 255.0;
+    (value, value, value)
+}
+
+const LIGHT: Palette = Palette {
+    border: gray(0x55),
+    fill: gray(0xF6),
+    complex_fill: gray(0xF6),
+    compartment_fill: gray(0xF6),
+    aux_line: gray(0x6A),
+    association_fill: gray(0x6B),
+    clone_marker_fill: gray(0xD1),
+    paper: gray(0xFF),
+};
+const DARK: Palette = Palette {
+    border: gray(0xD0),
+    fill: gray(0x2E),
+    complex_fill: gray(0x2E),
+    compartment_fill: gray(0x2E),
+    aux_line: gray(0xA0),
+    association_fill: gray(0xA8),
+    clone_marker_fill: gray(0x5A),
+    paper: gray(0x1E),
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ThemeName {
+    Light,
+    /// Light lines and text on dark fills, for dark slides and dashboards.
+    Dark,
+}
+
+/// How colors that carry meaning (overlay scales, highlight, background) are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PaletteMode {
+    Color,
+    /// Every color converted to its luminance, for print.
+    Grayscale,
+    /// Okabe-Ito colors (blue and vermillion scales, reddish purple highlights) that stay
+    /// distinguishable with color vision deficiency.
+    Colorblind,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Background {
+    /// Nothing is painted behind the map; PNG keeps an alpha channel.
+    Transparent,
+    Color((f64, f64, f64)),
+}
+
+impl Background {
+    /// Parse `transparent` or a `#rrggbb` color.
+    pub(crate) fn parse(value: &str) -> Result<Self> {
+        if value.trim().eq_ignore_ascii_case("transparent") {
+            Ok(Background::Transparent)
+        } else {
+            Ok(Background::Color(parse_hex_color(value)?))
+        }
+    }
+}
+
+/// Colors of everything drawn on the map except highlight and overlay colors.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Palette {
+    /// Glyph borders, arcs and text.
+    pub(crate) border: (f64, f64, f64),
+    /// Glyph interiors, and the neutral end of overlay color scales.
+    pub(crate) fill: (f64, f64, f64),
+    pub(crate) complex_fill: (f64, f64, f64),
+    pub(crate) compartment_fill: (f64, f64, f64),
+    /// Inner lines of aux units and the clone marker outline.
+    pub(crate) aux_line: (f64, f64, f64),
+    pub(crate) association_fill: (f64, f64, f64),
+    pub(crate) clone_marker_fill: (f64, f64, f64),
+    /// Interiors that must hide the lines behind them: open arrowheads, aux units and
+    /// stoichiometry boxes. Also the canvas color unless `--background` says otherwise.
+    pub(crate) paper: (f64, f64, f64),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Theme {
+    pub(crate) palette: Palette,
+    pub(crate) mode: PaletteMode,
+    pub(crate) background: Background,
+    /// Draw modulatory arc classes with distinct dash patterns as well as arrowheads.
+    pub(crate) arc_dashes: bool,
+}
+
+impl Theme {
+    /// The named palette on the given background, or on its own paper color.
+    pub(crate) fn new(
+        name: ThemeName,
+        mode: PaletteMode,
+        background: Option<Background>,
+        arc_dashes: bool,
+    ) -> Self {
+        let mut palette = match name {
+            ThemeName::Light => LIGHT,
+            ThemeName::Dark => DARK,
+        };
+        if mode == PaletteMode::Colorblind {
+            // Tell entity pools, complexes and compartments apart by hue as well as shape.
+            for (fill, tint) in [
+                (&mut palette.fill, COLORBLIND_ENTITY_TINT),
+                (&mut palette.complex_fill, COLORBLIND_COMPLEX_TINT),
+                (&mut palette.compartment_fill, COLORBLIND_COMPARTMENT_TINT),
+            ] {
+                *fill = mix(*fill, tint, COLORBLIND_TINT_AMOUNT);
+            }
+        }
+        let background = match background {
+            Some(Background::Color(color)) => Background::Color(adjust(mode, color)),
+            Some(Background::Transparent) => Background::Transparent,
+            None => Background::Color(palette.paper),
+        };
+        Self {
+            palette,
+            mode,
+            background,
+            arc_dashes,
+        }
+    }
+
+    /// A color as the palette mode shows it: its luminance in grayscale, unchanged otherwise.
+    pub(crate) fn adjust(&self, color: (f64, f64, f64)) -> (f64, f64, f64) {
+        adjust(self.mode, color)
+    }
+
+    /// Outline color that separates text from lines behind it: the background color, or
+    /// nothing on a transparent canvas where the slide color is unknown.
+    pub(crate) fn text_halo(&self) -> Option<(f64, f64, f64)> {
+        match self.background {
+            Background::Transparent => None,
+            Background::Color(color) => Some(color),
+        }
+    }
+}
+
+fn adjust(mode: PaletteMode, color: (f64, f64, f64)) -> (f64, f64, f64) {
+    match mode {
+        PaletteMode::Grayscale => {
+            // Rec. 709 luma weights.
+            let luma = 0.2126 * color.0 + 0.7152 * color.1 + 0.0722 * color.2;
+            (luma, luma, luma)
+        }
+        PaletteMode::Color | PaletteMode::Colorblind => color,
+    }
+}
+
+/// Linear blend from `from` (t = 0) to `to` (t = 1).
+pub(crate) fn mix(from: (f64, f64, f64), to: (f64, f64, f64), t: f64) -> (f64, f64, f64) {
+    let t = t.clamp(0.0, 1.0);
+    (
+        from.0 + (to.0 - from.0) * t,
+        from.1 + (to.1 - from.1) * t,
+        from.2 + (to.2 - from.2) * t,
+    )
+}
+
+/// Install the theme for this run; only the first call has an effect.
+pub(crate) fn set(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+pub(crate) fn current() -> &'static Theme {
+    THEME.get_or_init(|| Theme::new(ThemeName::Light, PaletteMode::Color, None, false))
+}
+
+pub(crate) fn palette() -> &'static Palette {
+    &current().palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colorblind_mode_tints_entity_complex_and_compartment_fills() {
+        let theme = Theme::new(ThemeName::Light, PaletteMode::Colorblind, None, false);
+        let palette = theme.palette;
+        assert_ne!(palette.fill, LIGHT.fill);
+        assert_ne!(palette.fill, palette.complex_fill);
+        assert_ne!(palette.complex_fill, palette.compartment_fill);
+        assert_eq!(palette.border, LIGHT.border);
+    }
+
+    #[test]
+    fn grayscale_mode_keeps_gray_fills_and_converts_colors() {
+        let theme = Theme::new(ThemeName::Light, PaletteMode::Grayscale, None, false);
+        assert_eq!(theme.palette.compartment_fill, LIGHT.compartment_fill);
+        let (r, g, b) = theme.adjust((0.9, 0.3, 0.2));
+        assert!(r == g && g == b);
+    }
+}